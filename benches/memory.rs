@@ -0,0 +1,146 @@
+//! Compares the Vec-backed `intcode::State` against the HashMap-backed interpreter it replaced.
+//!
+//!     cargo bench --bench memory
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ::aoc2019::intcode;
+
+/// Runs a program to completion, returning the value left at address 0 and every output.
+type Runner = fn(&[i64], &[i64]) -> (i64, Vec<i64>);
+
+fn parse(input: &str) -> Vec<i64> {
+    input.split(',').map(|x| x.trim().parse().unwrap()).collect()
+}
+
+/// The interpreter as it was before memory moved to a Vec, trimmed down to what the benchmarks
+/// need: no channels and no error reporting.
+struct HashMapState {
+    inputs: Vec<i64>,
+    program: HashMap<i64, i64>,
+    pc: i64,
+    relative_base: i64,
+}
+
+impl HashMapState {
+    fn from(program: &[i64], inputs: &[i64]) -> Self {
+        let mut state = HashMapState{inputs: inputs.to_vec(), program: HashMap::with_capacity(program.len()), pc: 0, relative_base: 0};
+
+        for (i, op) in program.iter().enumerate() {
+            state.program.insert(i as i64, *op);
+        }
+
+        state
+    }
+
+    fn param(&self, op: i64, param_position: i64) -> i64 {
+        *self.program.get(&(self.pc + param_position)).unwrap_or(&0)
+            + if (op / 10i64.pow(param_position as u32 + 1)) % 10 == 2 { self.relative_base } else { 0 }
+    }
+
+    fn value(&self, op: i64, param_position: i64) -> i64 {
+        if (op / 10i64.pow(param_position as u32 + 1)) % 10 == 1 {
+            *self.program.get(&(self.pc + param_position)).unwrap_or(&0)
+        } else {
+            *self.program.get(&self.param(op, param_position)).unwrap_or(&0)
+        }
+    }
+
+    fn run(&mut self) -> (i64, Vec<i64>) {
+        let mut outputs = Vec::new();
+
+        loop {
+            let op = *self.program.get(&self.pc).unwrap_or(&0);
+            match op % 100 {
+                1 | 2 | 7 | 8 => {
+                    let (a, b) = (self.value(op, 1), self.value(op, 2));
+                    let result = match op % 100 {
+                        1 => a + b,
+                        2 => a * b,
+                        7 => (a < b) as i64,
+                        _ => (a == b) as i64,
+                    };
+                    let addr = self.param(op, 3);
+                    self.program.insert(addr, result);
+                    self.pc += 4;
+                },
+                3 => {
+                    let addr = self.param(op, 1);
+                    let value = self.inputs.remove(0);
+                    self.program.insert(addr, value);
+                    self.pc += 2;
+                },
+                4 => {
+                    outputs.push(self.value(op, 1));
+                    self.pc += 2;
+                },
+                5 | 6 => {
+                    if (self.value(op, 1) != 0) == (op % 100 == 5) {
+                        self.pc = self.value(op, 2);
+                    } else {
+                        self.pc += 3;
+                    }
+                },
+                9 => {
+                    self.relative_base += self.value(op, 1);
+                    self.pc += 2;
+                },
+                _ => return (*self.program.get(&0).unwrap_or(&0), outputs),
+            }
+        }
+    }
+}
+
+fn run_vec(program: &[i64], inputs: &[i64]) -> (i64, Vec<i64>) {
+    let mut state = intcode::State::new(program);
+    inputs.iter().for_each(|input| state.push_input(*input));
+
+    let mut outputs = Vec::new();
+    while let intcode::Status::Output(value) = state.run().unwrap() {
+        outputs.push(value);
+    }
+    (state.memory.get(0), outputs)
+}
+
+fn run_hashmap(program: &[i64], inputs: &[i64]) -> (i64, Vec<i64>) {
+    HashMapState::from(program, inputs).run()
+}
+
+/// Day 2 part 2: try every noun/verb pair.
+fn noun_verb_sweep(program: &[i64], run: Runner) -> i64 {
+    let mut program = program.to_vec();
+
+    let mut checksum = 0;
+    for noun in 0..=99 {
+        for verb in 0..=99 {
+            program[1] = noun;
+            program[2] = verb;
+            checksum ^= run(&program, &[]).0;
+        }
+    }
+    checksum
+}
+
+fn bench<F: FnMut() -> i64>(name: &str, iterations: u32, mut f: F) -> (Duration, i64) {
+    let mut result = f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        result = f();
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!("{:<34} {:>10.3} ms", name, elapsed.as_secs_f64() * 1000.0);
+    (elapsed, result)
+}
+
+fn compare(name: &str, iterations: u32, f: fn(Runner) -> i64) {
+    let (hashmap, expected) = bench(&format!("{} (HashMap)", name), iterations, || f(run_hashmap));
+    let (vec, result) = bench(&format!("{} (Vec)", name), iterations, || f(run_vec));
+    assert_eq!(expected, result, "both backends should agree on {}", name);
+    println!("{:<34} {:>10.2}x", "speedup", hashmap.as_secs_f64() / vec.as_secs_f64());
+}
+
+fn main() {
+    compare("day 2 noun/verb sweep", 5, |run| noun_verb_sweep(&parse(include_str!("../inputs/02.txt")), run));
+    compare("day 9 BOOST, part 2", 3, |run| run(&parse(include_str!("../inputs/09.txt")), &[2]).1[0]);
+}
@@ -0,0 +1,475 @@
+//! Droid explorer for the text adventure of day 25. Rooms are printed as:
+//!
+//! ```text
+//! == Hull Breach ==
+//! You got in through a hole in the floor here.
+//!
+//! Doors here lead:
+//! - north
+//! - east
+//!
+//! Items here:
+//! - mug
+//!
+//! Command?
+//! ```
+//!
+//! The explorer maps every room it can reach, picks up whatever it's safe to carry, then finds
+//! which items get it past the pressure-sensitive floor behind the security checkpoint.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use pathfinding::prelude::bfs;
+
+use crate::intcode::{Ascii, AsciiOutput, IntcodeError};
+
+/// Most items `crack_checkpoint` tries combinations of, each one doubling their number.
+pub const MAX_ITEMS: usize = 16;
+
+/// Items that are known to end the game or leave the droid stuck.
+pub const TRAPS: &[&str] = &["infinite loop", "giant electromagnet", "molten lava", "escape pod", "photons"];
+
+/// What the explorer needs from the game.
+pub trait Console: Sized {
+    /// Runs until the game waits for a command or ends, returning what it printed.
+    fn read(&mut self) -> Result<String, IntcodeError>;
+    fn write(&mut self, command: &str);
+    fn is_over(&self) -> bool;
+    /// An independent copy of the game, to try things out on.
+    fn fork(&self) -> Self;
+}
+
+impl Console for Ascii {
+    fn read(&mut self) -> Result<String, IntcodeError> {
+        Ok(self.run()?.into_iter().map(|output| match output {
+            AsciiOutput::Line(line) => line + "\n",
+            AsciiOutput::Value(value) => format!("{}\n", value),
+        }).collect())
+    }
+
+    fn write(&mut self, command: &str) {
+        self.send_line(command);
+    }
+
+    fn is_over(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn fork(&self) -> Self {
+        Ascii::fork(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// Every room described in `text`, in order. Being thrown out of a room prints both rooms.
+pub fn parse_rooms(text: &str) -> Vec<Room> {
+    enum Section { Doors, Items }
+
+    let mut rooms: Vec<Room> = Vec::new();
+    let mut section = None;
+    for line in text.lines().map(|line| line.trim()) {
+        if line.starts_with("== ") && line.ends_with(" ==") && line.len() > 6 {
+            rooms.push(Room{name: line[3..line.len() - 3].to_string(), ..Room::default()});
+            section = None;
+            continue
+        }
+
+        let room = match rooms.last_mut() {
+            Some(room) => room,
+            None => continue,
+        };
+        match line {
+            "Doors here lead:" => section = Some(Section::Doors),
+            "Items here:" => section = Some(Section::Items),
+            "" => section = None,
+            _ if line.starts_with("- ") => match section {
+                Some(Section::Doors) => room.doors.push(line[2..].to_string()),
+                Some(Section::Items) => room.items.push(line[2..].to_string()),
+                None => {},
+            },
+            _ if room.description.is_empty() && section.is_none() => room.description = line.to_string(),
+            _ => {},
+        }
+    }
+
+    rooms
+}
+
+/// The code given once the droid makes it past the checkpoint.
+pub fn password(text: &str) -> Option<String> {
+    let start = text.find("typing ")? + "typing ".len();
+    let code = text[start..].chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+    if code.is_empty() { None } else { Some(code) }
+}
+
+fn opposite(direction: &str) -> Option<&'static str> {
+    match direction {
+        "north" => Some("south"),
+        "south" => Some("north"),
+        "east" => Some("west"),
+        "west" => Some("east"),
+        _ => None,
+    }
+}
+
+pub struct Explorer<C: Console = Ascii> {
+    console: C,
+    pub rooms: HashMap<String, Room>,
+    /// Where each door that's been found leads, by room and direction.
+    pub doors: HashMap<(String, String), String>,
+    /// Doors that turned out not to lead anywhere.
+    blocked: HashSet<(String, String)>,
+    pub here: String,
+    pub inventory: BTreeSet<String>,
+    pub traps: HashSet<String>,
+    /// The room and direction leading onto the pressure-sensitive floor.
+    pub checkpoint: Option<(String, String)>,
+}
+
+impl<C: Console> Explorer<C> {
+    /// Starts the game and reads the first room.
+    pub fn new(mut console: C) -> Result<Self, IntcodeError> {
+        let text = console.read()?;
+        let room = parse_rooms(&text).pop().unwrap_or_default();
+        let mut explorer = Explorer{
+            console,
+            rooms: HashMap::new(),
+            doors: HashMap::new(),
+            blocked: HashSet::new(),
+            here: room.name.clone(),
+            inventory: BTreeSet::new(),
+            traps: TRAPS.iter().map(|item| item.to_string()).collect(),
+            checkpoint: None,
+        };
+        explorer.enter(room)?;
+        Ok(explorer)
+    }
+
+    /// Sends a command and returns the game's answer.
+    pub fn send(&mut self, command: &str) -> Result<String, IntcodeError> {
+        self.console.write(command);
+        self.console.read()
+    }
+
+    /// Visits every room that can be reached without going past the checkpoint, picking up every
+    /// item that's safe along the way.
+    pub fn explore(&mut self) -> Result<(), IntcodeError> {
+        loop {
+            let rooms = &self.rooms;
+            let unexplored = |room: &String| rooms.get(room).map(|r| r.doors.iter().any(|door| self.unexplored(room, door))).unwrap_or(false);
+            let target = match bfs(&self.here, |room| self.neighbours(room), unexplored) {
+                Some(path) => path.last().unwrap().clone(),
+                None => return Ok(()),
+            };
+
+            self.go_to(&target)?;
+            let door = self.rooms[&target].doors.iter().find(|door| self.unexplored(&target, door)).unwrap().clone();
+            self.step(&door)?;
+        }
+    }
+
+    /// The directions to follow to get from the current room to `target`, through known doors.
+    pub fn path(&self, target: &str) -> Option<Vec<String>> {
+        let rooms = bfs(&self.here, |room| self.neighbours(room), |room| room == target)?;
+        Some(rooms.windows(2).map(|pair| {
+            self.doors.iter().find(|((from, _), to)| *from == pair[0] && **to == pair[1]).map(|((_, door), _)| door.clone()).unwrap()
+        }).collect())
+    }
+
+    /// Walks to `target`, returning whether there was a known way there.
+    pub fn go_to(&mut self, target: &str) -> Result<bool, IntcodeError> {
+        let path = match self.path(target) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        for door in path {
+            self.step(&door)?;
+        }
+        Ok(true)
+    }
+
+    /// Tries every combination of the items carried on the pressure-sensitive floor and returns
+    /// what the game printed once one got through. Combinations holding one that was too heavy,
+    /// or held by one that was too light, are skipped. Gives up if there's no known way to the
+    /// checkpoint, or more than `MAX_ITEMS` items to try.
+    pub fn crack_checkpoint(&mut self) -> Result<Option<String>, IntcodeError> {
+        let (room, door) = match self.checkpoint.clone() {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(None),
+        };
+        let items = self.inventory.iter().cloned().collect::<Vec<_>>();
+        if items.len() > MAX_ITEMS || !self.go_to(&room)? {
+            return Ok(None)
+        }
+
+        let mut too_heavy: Vec<u32> = Vec::new();
+        let mut too_light: Vec<u32> = Vec::new();
+        for set in 0..1u32 << items.len() {
+            if too_heavy.iter().any(|heavy| set & heavy == *heavy) || too_light.iter().any(|light| set & light == set) {
+                continue
+            }
+
+            for (i, item) in items.iter().enumerate() {
+                let carry = set & (1 << i) != 0;
+                if carry && !self.inventory.contains(item) {
+                    self.send(&format!("take {}", item))?;
+                    self.inventory.insert(item.clone());
+                } else if !carry && self.inventory.contains(item) {
+                    self.send(&format!("drop {}", item))?;
+                    self.inventory.remove(item);
+                }
+            }
+
+            let text = self.send(&door)?;
+            if text.contains("heavier than the detected value") {
+                too_light.push(set);
+            } else if text.contains("lighter than the detected value") {
+                too_heavy.push(set);
+            } else {
+                return Ok(Some(text))
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn unexplored(&self, room: &str, door: &str) -> bool {
+        let key = (room.to_string(), door.to_string());
+        !self.doors.contains_key(&key) && !self.blocked.contains(&key) && self.checkpoint.as_ref() != Some(&key)
+    }
+
+    fn neighbours(&self, room: &str) -> Vec<String> {
+        self.doors.iter().filter(|((from, _), _)| from == room).map(|(_, to)| to.clone()).collect()
+    }
+
+    /// Goes through `door`, mapping whatever is behind it.
+    fn step(&mut self, door: &str) -> Result<(), IntcodeError> {
+        let text = self.send(door)?;
+        let key = (self.here.clone(), door.to_string());
+
+        if text.contains("ejected back to the checkpoint") {
+            self.checkpoint = Some(key);
+            return Ok(())
+        }
+        let room = match parse_rooms(&text).pop() {
+            Some(room) => room,
+            None => {
+                self.blocked.insert(key);
+                return Ok(())
+            },
+        };
+
+        self.doors.insert(key, room.name.clone());
+        if let Some(back) = opposite(door) {
+            let here = &self.here;
+            self.doors.entry((room.name.clone(), back.to_string())).or_insert_with(|| here.clone());
+        }
+        self.here = room.name.clone();
+        if !self.rooms.contains_key(&room.name) {
+            self.enter(room)?;
+        }
+        Ok(())
+    }
+
+    /// Records a room seen for the first time and picks up its items.
+    fn enter(&mut self, room: Room) -> Result<(), IntcodeError> {
+        for item in &room.items {
+            if self.traps.contains(item) {
+                continue
+            }
+            if self.is_safe(item, &room)? {
+                self.send(&format!("take {}", item))?;
+                self.inventory.insert(item.clone());
+            } else {
+                self.traps.insert(item.clone());
+            }
+        }
+        self.rooms.insert(room.name.clone(), room);
+        Ok(())
+    }
+
+    /// Picks the item up on a copy of the game, then checks the droid still gets to move.
+    fn is_safe(&self, item: &str, room: &Room) -> Result<bool, IntcodeError> {
+        let mut game = self.console.fork();
+        game.write(&format!("take {}", item));
+        game.read()?;
+        if game.is_over() {
+            return Ok(false)
+        }
+
+        if let Some(door) = room.doors.first() {
+            game.write(door);
+            let text = game.read()?;
+            if game.is_over() || text.contains("can't move") {
+                return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small adventure:
+    ///
+    /// ```text
+    /// Kitchen (mug, molten lava, cursed coin)
+    ///    |
+    /// Hull Breach (rock) -- Security Checkpoint -- Pressure-Sensitive Floor
+    ///                              |
+    ///                         Observatory (feather)
+    /// ```
+    ///
+    /// Getting past the floor takes the mug and the rock; the cursed coin ends the game.
+    #[derive(Clone)]
+    struct Game {
+        here: &'static str,
+        items: HashMap<&'static str, Vec<&'static str>>,
+        inventory: Vec<&'static str>,
+        pending: String,
+        over: bool,
+    }
+
+    const MAP: &[(&str, &str, &str)] = &[
+        ("Hull Breach", "north", "Kitchen"),
+        ("Kitchen", "south", "Hull Breach"),
+        ("Hull Breach", "east", "Security Checkpoint"),
+        ("Security Checkpoint", "west", "Hull Breach"),
+        ("Security Checkpoint", "south", "Observatory"),
+        ("Observatory", "north", "Security Checkpoint"),
+        ("Security Checkpoint", "east", "Pressure-Sensitive Floor"),
+    ];
+
+    fn weight(item: &str) -> u32 {
+        match item {
+            "mug" => 3,
+            "rock" => 5,
+            _ => 1,
+        }
+    }
+
+    impl Game {
+        fn new() -> Self {
+            let mut items = HashMap::new();
+            items.insert("Hull Breach", vec!["rock"]);
+            items.insert("Kitchen", vec!["mug", "molten lava", "cursed coin"]);
+            items.insert("Observatory", vec!["feather"]);
+            let mut game = Game{here: "Hull Breach", items, inventory: Vec::new(), pending: String::new(), over: false};
+            game.pending = game.describe("Hull Breach");
+            game
+        }
+
+        fn describe(&self, room: &str) -> String {
+            let mut text = format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", room);
+            for (_, door, _) in MAP.iter().filter(|(from, _, _)| *from == room) {
+                text += &format!("- {}\n", door);
+            }
+            if let Some(items) = self.items.get(room).filter(|items| !items.is_empty()) {
+                text += "\nItems here:\n";
+                for item in items {
+                    text += &format!("- {}\n", item);
+                }
+            }
+            text + "\nCommand?\n"
+        }
+    }
+
+    impl Console for Game {
+        fn read(&mut self) -> Result<String, IntcodeError> {
+            Ok(std::mem::take(&mut self.pending))
+        }
+
+        fn write(&mut self, command: &str) {
+            if let Some(item) = command.strip_prefix("take ") {
+                let items = self.items.get_mut(self.here).unwrap();
+                let i = items.iter().position(|i| *i == item).unwrap();
+                self.inventory.push(items.remove(i));
+                self.over = item == "cursed coin";
+                self.pending = format!("\nYou take the {}.\n\nCommand?\n", item);
+            } else if let Some(item) = command.strip_prefix("drop ") {
+                let i = self.inventory.iter().position(|i| *i == item).unwrap();
+                let item = self.inventory.remove(i);
+                self.items.entry(self.here).or_default().push(item);
+                self.pending = format!("\nYou drop the {}.\n\nCommand?\n", item);
+            } else if let Some((_, _, to)) = MAP.iter().find(|(from, door, _)| *from == self.here && *door == command) {
+                if *to == "Pressure-Sensitive Floor" {
+                    let weight = self.inventory.iter().map(|item| weight(item)).sum::<u32>();
+                    let floor = "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- west\n\n";
+                    if weight == 8 {
+                        self.over = true;
+                        self.pending = format!("{}A loud, robotic voice says \"Analysis complete! You may proceed.\"\n\"You should be able to get in by typing 4242 on the keypad.\"\n", floor);
+                    } else {
+                        let comparison = if weight < 8 { "heavier" } else { "lighter" };
+                        self.pending = format!("{}A loud, robotic voice says \"Alert! Droids on this ship are {} than the detected value!\" and you are ejected back to the checkpoint.\n{}", floor, comparison, self.describe(self.here));
+                    }
+                } else {
+                    self.here = to;
+                    self.pending = self.describe(to);
+                }
+            } else {
+                self.pending = "\nYou can't go that way.\n\nCommand?\n".to_string();
+            }
+        }
+
+        fn is_over(&self) -> bool {
+            self.over
+        }
+
+        fn fork(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_parse_rooms() {
+        let rooms = parse_rooms(&Game::new().describe("Kitchen"));
+        assert_eq!(rooms, vec![Room{
+            name: "Kitchen".to_string(),
+            description: "A room.".to_string(),
+            doors: vec!["south".to_string()],
+            items: vec!["mug".to_string(), "molten lava".to_string(), "cursed coin".to_string()],
+        }]);
+        assert_eq!(password("by typing 4242 on the keypad"), Some("4242".to_string()));
+        assert_eq!(password("no luck"), None);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut explorer = Explorer::new(Game::new()).unwrap();
+        explorer.explore().unwrap();
+
+        let mut rooms = explorer.rooms.keys().cloned().collect::<Vec<_>>();
+        rooms.sort();
+        assert_eq!(rooms, vec!["Hull Breach", "Kitchen", "Observatory", "Security Checkpoint"]);
+        assert_eq!(explorer.inventory.iter().collect::<Vec<_>>(), vec!["feather", "mug", "rock"]);
+        assert!(explorer.traps.contains("cursed coin"));
+        assert_eq!(explorer.checkpoint, Some(("Security Checkpoint".to_string(), "east".to_string())));
+
+        assert_eq!(explorer.go_to("Engineering"), Ok(false));
+        assert_eq!(explorer.go_to("Kitchen"), Ok(true));
+        assert_eq!(explorer.path("Observatory"), Some(vec!["south".to_string(), "east".to_string(), "south".to_string()]));
+
+        let text = explorer.crack_checkpoint().unwrap().unwrap();
+        assert_eq!(password(&text), Some("4242".to_string()));
+        assert_eq!(explorer.inventory.iter().collect::<Vec<_>>(), vec!["mug", "rock"]);
+    }
+
+    #[test]
+    fn test_too_many_items() {
+        let mut explorer = Explorer::new(Game::new()).unwrap();
+        explorer.explore().unwrap();
+        explorer.inventory.extend((0..MAX_ITEMS).map(|i| format!("pebble {}", i)));
+        let here = explorer.here.clone();
+        assert_eq!(explorer.crack_checkpoint(), Ok(None));
+        assert_eq!(explorer.here, here);
+    }
+}
@@ -0,0 +1,257 @@
+//! Arcade cabinet for day 13: the game outputs (x, y, tile) triples to draw the screen, with
+//! (-1, 0, score) updating the score, and reads the joystick position (-1, 0 or 1) every frame.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::intcode::{IntcodeError, State, Status};
+
+mod replay;
+mod search;
+
+pub use self::replay::{Ending, Replay, ReplayError};
+pub use self::search::{beam_search, search, SearchError, Solution};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Tile {
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+impl Tile {
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '-',
+            Tile::Ball => 'o',
+        }
+    }
+}
+
+/// Tiles by id, the id being handed back if it isn't one of a tile.
+impl TryFrom<i64> for Tile {
+    type Error = i64;
+
+    fn try_from(i: i64) -> Result<Self, i64> {
+        match i {
+            1 => Ok(Tile::Wall),
+            2 => Ok(Tile::Block),
+            3 => Ok(Tile::Paddle),
+            4 => Ok(Tile::Ball),
+            _ => Err(i),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Arcade {
+    pub state: State,
+    /// Every tile that isn't empty.
+    pub screen: HashMap<(i64, i64), Tile>,
+    pub score: i64,
+    /// Number of joystick inputs read so far.
+    pub frame: usize,
+    /// The frames on which the joystick was moved, and where to.
+    pub moves: Vec<(usize, i64)>,
+    halted: bool,
+}
+
+impl Arcade {
+    pub fn new(program: &[i64]) -> Self {
+        Arcade{state: State::new(program), screen: HashMap::new(), score: 0, frame: 0, moves: Vec::new(), halted: false}
+    }
+
+    /// A cabinet with quarters inserted, which plays instead of drawing the screen once.
+    pub fn free_play(program: &[i64]) -> Self {
+        let mut arcade = Arcade::new(program);
+        arcade.state.memory.set(0, 2).unwrap();
+        arcade
+    }
+
+    /// Runs the game until it reads the joystick or ends, drawing what it outputs meanwhile.
+    /// Tiles with an unknown id aren't drawn.
+    pub fn update(&mut self) -> Result<(), IntcodeError> {
+        self.halted = self.state.run_to_input()? == Status::Halted;
+        while self.state.outputs.len() >= 3 {
+            let x = self.state.outputs.pop_front().unwrap();
+            let y = self.state.outputs.pop_front().unwrap();
+            let tile = self.state.outputs.pop_front().unwrap();
+
+            if x == -1 && y == 0 {
+                self.score = tile;
+            } else if tile == 0 {
+                self.screen.remove(&(x, y));
+            } else if let Ok(tile) = Tile::try_from(tile) {
+                self.screen.insert((x, y), tile);
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the joystick left (-1), right (1) or leaves it in the middle (0) for the next frame.
+    pub fn joystick(&mut self, position: i64) -> Result<(), IntcodeError> {
+        self.state.push_input(position);
+        if position != 0 {
+            self.moves.push((self.frame, position));
+        }
+        self.frame += 1;
+        self.update()
+    }
+
+    /// Plays until the game ends, asking `strategy` where to move the joystick every frame, and
+    /// returns the final score.
+    pub fn play<F: FnMut(&Arcade) -> i64>(&mut self, mut strategy: F) -> Result<i64, IntcodeError> {
+        self.update()?;
+        while !self.halted {
+            let position = strategy(self);
+            self.joystick(position)?;
+        }
+        Ok(self.score)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// An independent copy of the cabinet, to try moves out on.
+    pub fn fork(&self) -> Self {
+        Arcade{
+            state: self.state.fork(),
+            screen: self.screen.clone(),
+            score: self.score,
+            frame: self.frame,
+            moves: self.moves.clone(),
+            halted: self.halted,
+        }
+    }
+
+    fn find(&self, tile: Tile) -> Option<(i64, i64)> {
+        self.screen.iter().find(|(_, t)| **t == tile).map(|(&position, _)| position)
+    }
+
+    pub fn ball(&self) -> Option<(i64, i64)> {
+        self.find(Tile::Ball)
+    }
+
+    pub fn paddle(&self) -> Option<(i64, i64)> {
+        self.find(Tile::Paddle)
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.screen.values().filter(|tile| **tile == Tile::Block).count()
+    }
+
+    /// Moves the paddle towards the ball.
+    pub fn follow(&self) -> i64 {
+        match (self.ball(), self.paddle()) {
+            (Some((ball, _)), Some((paddle, _))) => (ball - paddle).signum(),
+            _ => 0,
+        }
+    }
+
+    /// Draws the screen, from (0, 0) to the furthest tile, followed by the score. Trailing blanks
+    /// are left out.
+    pub fn render(&self) -> String {
+        let width = self.screen.keys().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let height = self.screen.keys().map(|&(_, y)| y + 1).max().unwrap_or(0);
+
+        let mut out = String::new();
+        for y in 0..height {
+            let row = (0..width).map(|x| self.screen.get(&(x, y)).map(|tile| tile.to_char()).unwrap_or(' ')).collect::<String>();
+            out += row.trim_end();
+            out.push('\n');
+        }
+        out += &format!("Score: {}\n", self.score);
+        out
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // a wall, a block and a ball above the paddle's row; the game is won once the paddle is under
+    // the ball, scoring one point per frame it took, and lost if the paddle hits the wall. Starts
+    // with an instruction that still works once quarters are inserted, turning it into a `mul`
+    pub(crate) const GAME: &str = "
+                add tmp, tmp, tmp
+                out #0
+                out #0
+                out #1
+                out #2
+                out #0
+                out #2
+                out #3
+                out #0
+                out #4
+        loop:   out px
+                out #1
+                out #3
+                in dir
+                add frame, #1, frame
+                out px
+                out #1
+                out #0
+                add px, dir, px
+                eq px, #0, tmp
+                jnz tmp, #lost
+                eq px, #3, tmp
+                jz tmp, #loop
+                out #-1
+                out #0
+                out frame
+                out #2
+                out #0
+                out #0
+        lost:   halt
+        px:     db 1
+        dir:    db 0
+        frame:  db 0
+        tmp:    db 0
+    ";
+
+    #[test]
+    fn test_render() {
+        let mut arcade = Arcade::new(&assemble(GAME).unwrap());
+        arcade.update().unwrap();
+        assert_eq!(arcade.render(), "\
+# =o
+ -
+Score: 0
+");
+        assert_eq!(arcade.blocks(), 1);
+        assert_eq!(arcade.ball(), Some((3, 0)));
+        assert_eq!(arcade.paddle(), Some((1, 1)));
+        assert!(!arcade.is_halted());
+    }
+
+    #[test]
+    fn test_play() {
+        let mut arcade = Arcade::new(&assemble(GAME).unwrap());
+        assert_eq!(arcade.play(Arcade::follow), Ok(2));
+        assert_eq!(arcade.frame, 2);
+        assert_eq!(arcade.moves, vec![(0, 1), (1, 1)]);
+        assert_eq!(arcade.blocks(), 0);
+        assert!(arcade.is_halted());
+
+        let mut arcade = Arcade::new(&assemble(GAME).unwrap());
+        assert_eq!(arcade.play(|_| -1), Ok(0));
+        assert_eq!(arcade.frame, 1);
+        assert_eq!(arcade.blocks(), 1);
+    }
+
+    #[test]
+    fn test_unknown_tile() {
+        assert_eq!(Tile::try_from(4), Ok(Tile::Ball));
+        assert_eq!(Tile::try_from(7), Err(7));
+
+        let mut arcade = Arcade::new(&[104,1,104,2,104,7,104,3,104,2,104,4,99]);
+        arcade.update().unwrap();
+        assert_eq!(arcade.screen.len(), 1);
+        assert_eq!(arcade.ball(), Some((3, 2)));
+    }
+}
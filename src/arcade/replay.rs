@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::Arcade;
+use crate::intcode::fields::{invalid, parse, Fields};
+use crate::intcode::IntcodeError;
+
+const HEADER: &str = "arcade-replay";
+const VERSION: u32 = 1;
+
+/// How a game ended, or how far it got.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Ending {
+    pub frames: usize,
+    pub score: i64,
+    pub blocks: usize,
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "score {} with {} blocks left after {} frames", self.score, self.blocks, self.frames)
+    }
+}
+
+/// A recorded game: the frames on which the joystick was moved, the joystick staying in the
+/// middle on every other frame, and how the game ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Replay {
+    pub moves: Vec<(usize, i64)>,
+    pub ending: Ending,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    Intcode(IntcodeError),
+    /// The game played out differently than it was recorded.
+    Mismatch { expected: Ending, actual: Ending },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Intcode(ref error) => write!(f, "{}", error),
+            ReplayError::Mismatch { expected, actual } => write!(f, "expected {}, got {}", expected, actual),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<IntcodeError> for ReplayError {
+    fn from(error: IntcodeError) -> Self {
+        ReplayError::Intcode(error)
+    }
+}
+
+/// Replays are stored as text, one field per line after a version header, moves being written
+/// as `frame:position`:
+///
+/// ```text
+/// arcade-replay 1
+/// frames 2
+/// score 2
+/// blocks 0
+/// moves 0:1,1:1
+/// ```
+impl Replay {
+    /// Where the joystick was on `frame`.
+    pub fn position(&self, frame: usize) -> i64 {
+        match self.moves.binary_search_by_key(&frame, |&(frame, _)| frame) {
+            Ok(i) => self.moves[i].1,
+            Err(_) => 0,
+        }
+    }
+
+    /// Plays the recorded moves on a fresh cabinet running `program`, for at most as many frames
+    /// as were recorded.
+    pub fn play(&self, program: &[i64]) -> Result<Arcade, IntcodeError> {
+        let mut arcade = Arcade::free_play(program);
+        arcade.update()?;
+        while !arcade.is_halted() && arcade.frame < self.ending.frames {
+            arcade.joystick(self.position(arcade.frame))?;
+        }
+        Ok(arcade)
+    }
+
+    /// Whether `arcade` ended up the way the recorded game did.
+    pub fn check(&self, arcade: &Arcade) -> Result<(), ReplayError> {
+        let actual = arcade.ending();
+        if actual == self.ending {
+            Ok(())
+        } else {
+            Err(ReplayError::Mismatch{expected: self.ending, actual})
+        }
+    }
+
+    /// Plays the replay back and checks it ends the same way.
+    pub fn verify(&self, program: &[i64]) -> Result<Arcade, ReplayError> {
+        let arcade = self.play(program)?;
+        self.check(&arcade)?;
+        Ok(arcade)
+    }
+
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{} {}", HEADER, VERSION)?;
+        writeln!(out, "frames {}", self.ending.frames)?;
+        writeln!(out, "score {}", self.ending.score)?;
+        writeln!(out, "blocks {}", self.ending.blocks)?;
+        writeln!(out, "moves {}", self.moves.iter().map(|(frame, position)| format!("{}:{}", frame, position)).collect::<Vec<_>>().join(","))
+    }
+
+    pub fn read_from<R: BufRead>(input: R) -> io::Result<Replay> {
+        let mut fields = Fields::open(input, HEADER, VERSION, "replay")?;
+        let frames = fields.parse("frames")?;
+        let score = fields.parse("score")?;
+        let blocks = fields.parse("blocks")?;
+        let moves = fields.next("moves")?.split(',').filter(|m| !m.is_empty()).map(|m| {
+            let mut parts = m.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(frame), Some(position)) => Ok((parse("move", frame)?, parse("move", position)?)),
+                _ => Err(invalid(format!("invalid move: {}", m))),
+            }
+        }).collect::<io::Result<Vec<_>>>()?;
+
+        if moves.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(invalid("moves aren't in frame order".to_string()))
+        }
+
+        Ok(Replay{moves, ending: Ending{frames, score, blocks}})
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        Replay::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+impl Arcade {
+    pub fn ending(&self) -> Ending {
+        Ending{frames: self.frame, score: self.score, blocks: self.blocks()}
+    }
+
+    /// The game played so far, as a replay.
+    pub fn replay(&self) -> Replay {
+        Replay{moves: self.moves.clone(), ending: self.ending()}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcade::tests::GAME;
+    use crate::intcode::assemble;
+
+    #[test]
+    fn test_replay() {
+        let program = assemble(GAME).unwrap();
+        let mut arcade = Arcade::free_play(&program);
+        arcade.play(Arcade::follow).unwrap();
+        let replay = arcade.replay();
+        assert_eq!(replay, Replay{moves: vec![(0, 1), (1, 1)], ending: Ending{frames: 2, score: 2, blocks: 0}});
+
+        let mut text = Vec::new();
+        replay.write_to(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text.clone()).unwrap(), "arcade-replay 1\nframes 2\nscore 2\nblocks 0\nmoves 0:1,1:1\n");
+        assert_eq!(Replay::read_from(&text[..]).unwrap(), replay);
+
+        let replayed = replay.verify(&program).unwrap();
+        assert_eq!(replayed.render(), arcade.render());
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let program = assemble(GAME).unwrap();
+        let replay = Replay{moves: vec![(0, 1)], ending: Ending{frames: 2, score: 2, blocks: 0}};
+        assert_eq!(replay.position(0), 1);
+        assert_eq!(replay.position(1), 0);
+        assert_eq!(replay.verify(&program).unwrap_err(), ReplayError::Mismatch{
+            expected: Ending{frames: 2, score: 2, blocks: 0},
+            actual: Ending{frames: 2, score: 0, blocks: 1},
+        });
+
+        assert!(Replay::read_from(&b"arcade-replay 1\nframes 2\nscore 2\nblocks 0\nmoves 1:1,0:1\n"[..]).is_err());
+        assert!(Replay::read_from(&b"arcade-replay 2\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use super::{Arcade, Replay, Tile};
+use crate::intcode::IntcodeError;
+
+/// How many columns on each side of where the ball comes down are tried for the paddle.
+const SPREAD: i64 = 1;
+
+/// Most frames waited for the ball to come down to the paddle, after which the game is taken to
+/// be stuck.
+const MAX_FRAMES: usize = 100_000;
+
+/// A winning game found by `search` or `beam_search`.
+#[derive(Debug)]
+pub struct Solution {
+    pub replay: Replay,
+    /// Instructions executed over every branch explored, dead ends included.
+    pub steps: usize,
+    /// Number of times the game was forked to try a position for the paddle.
+    pub branches: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchError {
+    Intcode(IntcodeError),
+    /// The ball didn't come down to the paddle within `MAX_FRAMES` frames of `frame`.
+    Stuck { frame: usize },
+    /// The exact search forked the game more times than it was allowed to.
+    TooManyBranches { branches: usize },
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SearchError::Intcode(ref error) => write!(f, "{}", error),
+            SearchError::Stuck { frame } => write!(f, "the ball didn't come down within {} frames of frame {}", MAX_FRAMES, frame),
+            SearchError::TooManyBranches { branches } => write!(f, "gave up after {} branches", branches),
+        }
+    }
+}
+
+impl Error for SearchError {}
+
+impl From<IntcodeError> for SearchError {
+    fn from(error: IntcodeError) -> Self {
+        SearchError::Intcode(error)
+    }
+}
+
+/// Counts of the work done by a search, across every game it forked.
+#[derive(Debug, Default)]
+struct Work {
+    steps: usize,
+    branches: usize,
+}
+
+/// A game waiting to be explored by `search`, the one with the fewest moves coming out of the
+/// heap first, then the one that was forked first.
+struct Branch {
+    arcade: Arcade,
+    order: usize,
+}
+
+impl Branch {
+    fn key(&self) -> (Reverse<usize>, Reverse<usize>) {
+        (Reverse(self.arcade.moves.len()), Reverse(self.order))
+    }
+}
+
+impl PartialEq for Branch {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Branch {}
+
+impl PartialOrd for Branch {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Branch {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Searches for the winning game with the fewest joystick moves. Between two bounces, all that
+/// matters is where the paddle is when the ball comes down: the game is forked whenever the ball
+/// heads for the paddle's row, once for every column around where it lands, the paddle going
+/// straight there and waiting. Branches that lose the ball are dropped.
+///
+/// Games are explored in order of the moves they took, as in Dijkstra's algorithm, so the first
+/// one won took the fewest moves there are. The number of games grows with every bounce though,
+/// so the search gives up once it has forked more than `max_branches` of them.
+pub fn search(program: &[i64], max_branches: usize) -> Result<Option<Solution>, SearchError> {
+    let mut start = Arcade::free_play(program);
+    start.update()?;
+
+    let mut work = Work{steps: start.state.steps, branches: 0};
+    let mut seen = HashSet::new();
+    let mut queue = BinaryHeap::new();
+    let mut order = 0;
+    queue.push(Branch{arcade: start, order});
+
+    while let Some(Branch{arcade, ..}) = queue.pop() {
+        if arcade.is_halted() {
+            if arcade.blocks() == 0 {
+                return Ok(Some(Solution{replay: arcade.replay(), steps: work.steps, branches: work.branches}))
+            }
+            continue
+        }
+        // branches can end up in the exact same spot, catching the ball with the paddle moving
+        // in from either side, or come back to where they already were a few bounces earlier
+        if !seen.insert(fingerprint(&arcade)) {
+            continue
+        }
+        if work.branches > max_branches {
+            return Err(SearchError::TooManyBranches { branches: work.branches })
+        }
+        for next in arcade.branch(&mut work)? {
+            order += 1;
+            queue.push(Branch{arcade: next, order});
+        }
+    }
+
+    Ok(None)
+}
+
+/// Searches for a winning game with few joystick moves, quicker than `search` but without any
+/// guarantee that the game found has the fewest. Games are forked the same way, bounce after
+/// bounce, but only the `width` games with the fewest moves, then the fewest blocks left, are
+/// kept after each bounce, along with none that already took more moves than a game that was
+/// won. A game dropped early on could have led to fewer moves: the game found only gets closer
+/// to the shortest as the beam gets wider.
+pub fn beam_search(program: &[i64], width: usize) -> Result<Option<Solution>, SearchError> {
+    let mut start = Arcade::free_play(program);
+    start.update()?;
+
+    let mut work = Work{steps: start.state.steps, branches: 0};
+    let mut best: Option<Arcade> = None;
+    let mut seen = HashSet::new();
+    let mut beam = vec![start];
+
+    while !beam.is_empty() {
+        let mut next = Vec::new();
+        for arcade in beam {
+            next.extend(arcade.branch(&mut work)?);
+        }
+
+        let (ended, mut playing): (Vec<_>, Vec<_>) = next.into_iter().partition(Arcade::is_halted);
+        for arcade in ended.into_iter().filter(|arcade| arcade.blocks() == 0) {
+            if best.as_ref().map(|best| arcade.moves.len() < best.moves.len()).unwrap_or(true) {
+                best = Some(arcade);
+            }
+        }
+
+        if let Some(ref best) = best {
+            playing.retain(|arcade| arcade.moves.len() < best.moves.len());
+        }
+        playing.sort_by_key(|arcade| (arcade.moves.len(), arcade.blocks()));
+        playing.retain(|arcade| seen.insert(fingerprint(arcade)));
+        playing.truncate(width);
+        beam = playing;
+    }
+
+    Ok(best.map(|arcade| Solution{replay: arcade.replay(), steps: work.steps, branches: work.branches}))
+}
+
+/// Hash of the whole machine, which is all there is to the game.
+fn fingerprint(arcade: &Arcade) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (arcade.state.pc, arcade.state.relative_base, arcade.state.memory.as_slice()).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Arcade {
+    /// The games the paddle can make of the ball's next bounce, keeping those where it's caught,
+    /// or the game as it ends if it does before the ball comes down.
+    fn branch(&self, work: &mut Work) -> Result<Vec<Arcade>, SearchError> {
+        let (paddle, row) = match self.paddle() {
+            Some(paddle) => paddle,
+            None => return Ok(Vec::new()),
+        };
+
+        // see where the ball comes down if the paddle stays put
+        let mut lookahead = self.fork();
+        let landing = lookahead.landing(row)?;
+        work.steps += lookahead.state.steps - self.state.steps;
+        let (frames, x) = match landing {
+            Some(landing) => landing,
+            // the game ended before the ball came down, won or lost
+            None => return Ok(vec![lookahead]),
+        };
+
+        let mut caught = Vec::new();
+        for column in x - SPREAD..=x + SPREAD {
+            if (column - paddle).abs() > frames as i64 + 1 || self.screen.get(&(column, row)) == Some(&Tile::Wall) {
+                continue
+            }
+
+            let mut branch = self.fork();
+            work.branches += 1;
+            let landed = branch.bounce(column, row)?;
+            work.steps += branch.state.steps - self.state.steps;
+            if landed {
+                caught.push(branch);
+            }
+        }
+        Ok(caught)
+    }
+
+    /// Leaves the joystick in the middle until the ball is right above the paddle's `row`,
+    /// returning how many frames that took and the ball's column, or `None` if the game ended.
+    fn landing(&mut self, row: i64) -> Result<Option<(usize, i64)>, SearchError> {
+        let start = self.frame;
+        loop {
+            if self.frame - start > MAX_FRAMES {
+                return Err(SearchError::Stuck { frame: start })
+            }
+            match self.ball() {
+                Some((x, y)) if y == row - 1 => return Ok(Some((self.frame - start, x))),
+                _ => {},
+            }
+            if self.is_halted() {
+                return Ok(None)
+            }
+            self.joystick(0)?;
+        }
+    }
+
+    /// Moves the paddle straight to `column` and leaves it there until the ball has come down to
+    /// the paddle's `row` and gone back up, returning whether it did. Winning counts as well.
+    fn bounce(&mut self, column: i64, row: i64) -> Result<bool, SearchError> {
+        let start = self.frame;
+        let mut landed = false;
+        loop {
+            if self.frame - start > MAX_FRAMES {
+                return Err(SearchError::Stuck { frame: start })
+            }
+            let position = self.paddle().map(|(paddle, _)| (column - paddle).signum()).unwrap_or(0);
+            self.joystick(position)?;
+            if self.is_halted() {
+                return Ok(self.blocks() == 0)
+            }
+
+            match self.ball() {
+                Some((_, y)) if y >= row => return Ok(false),
+                Some((_, y)) if y == row - 1 => landed = true,
+                Some(_) if landed => return Ok(true),
+                _ => {},
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // the ball drops straight down column 3 towards the paddle's row 4, bouncing back up only if
+    // the paddle is right below it; every time it makes it back up to row 1 it breaks one of the
+    // two blocks, breaking the last one winning the game
+    const DROP: &str = "
+                add tmp, tmp, tmp
+                out #0
+                out #0
+                out #2
+                out #1
+                out #0
+                out #2
+                out #3
+                out by
+                out #4
+        loop:   out px
+                out #4
+                out #3
+                in dir
+                out px
+                out #4
+                out #0
+                add px, dir, px
+                out px
+                out #4
+                out #3
+                out #3
+                out by
+                out #0
+                eq by, #3, tmp
+                jz tmp, #move
+                eq px, #3, tmp
+                jz tmp, #lost
+                mul vy, #-1, vy
+        move:   add by, vy, by
+                eq by, #1, tmp
+                jz tmp, #draw
+                add blocks, #-1, blocks
+                out blocks
+                out #0
+                out #0
+                mul vy, #-1, vy
+                jz blocks, #won
+        draw:   out #3
+                out by
+                out #4
+                jz #0, #loop
+        won:    out #-1
+                out #0
+                out #100
+        lost:   halt
+        px:     db 1
+        by:     db 1
+        vy:     db 1
+        blocks: db 2
+        dir:    db 0
+        tmp:    db 0
+    ";
+
+    #[test]
+    fn test_solve() {
+        let program = assemble(DROP).unwrap();
+        let solution = search(&program, 100).unwrap().unwrap();
+        assert_eq!(solution.replay.moves, vec![(0, 1), (1, 1)]);
+        assert_eq!(solution.replay.ending.score, 100);
+        assert_eq!(solution.replay.ending.blocks, 0);
+        // the paddle is tried on either side of the ball on both bounces
+        assert_eq!(solution.branches, 6);
+        assert!(solution.replay.verify(&program).is_ok());
+
+        let mut greedy = Arcade::free_play(&program);
+        greedy.play(Arcade::follow).unwrap();
+        assert!(solution.steps > greedy.state.steps);
+
+        let beam = beam_search(&program, 1).unwrap().unwrap();
+        assert_eq!(beam.replay.moves, solution.replay.moves);
+        assert_eq!(search(&program, 0).unwrap_err(), SearchError::TooManyBranches { branches: 3 });
+    }
+
+    #[test]
+    fn test_stuck() {
+        // the ball hangs in the middle of the screen
+        let program = assemble(&DROP.replace("by:     db 1", "by:     db 2").replace("vy:     db 1", "vy:     db 0")).unwrap();
+        assert_eq!(search(&program, 100).unwrap_err(), SearchError::Stuck { frame: 0 });
+        assert_eq!(beam_search(&program, 1).unwrap_err(), SearchError::Stuck { frame: 0 });
+    }
+
+    #[test]
+    fn test_unwinnable() {
+        // the paddle can't move
+        let program = assemble(&DROP.replace("add px, dir, px", "add px, #0, px")).unwrap();
+        assert!(search(&program, 100).unwrap().is_none());
+        assert!(beam_search(&program, 10).unwrap().is_none());
+    }
+}
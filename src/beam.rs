@@ -0,0 +1,204 @@
+//! Tractor beam scanner for day 19: the drone program reads a position and outputs whether it's
+//! pulled by the beam. Every query is a run of the program from scratch, so the drone is reset
+//! from a snapshot rather than rebuilt, and scans follow the edges of the beam, which only move
+//! right as rows go down, instead of querying every position.
+
+use crate::intcode::{IntcodeError, Snapshot, State, Status};
+
+pub struct Beam {
+    drone: State,
+    initial: Snapshot,
+    /// Number of positions queried so far.
+    pub queries: usize,
+}
+
+impl Beam {
+    pub fn new(program: &[i64]) -> Self {
+        let drone = State::new(program);
+        let initial = drone.snapshot();
+        Beam{drone, initial, queries: 0}
+    }
+
+    /// Whether the beam pulls at (x, y). A drone that asks for more than the position is reported
+    /// as having run out of input.
+    pub fn query(&mut self, x: i64, y: i64) -> Result<bool, IntcodeError> {
+        self.queries += 1;
+        self.drone.restore(&self.initial);
+        self.drone.push_input(x);
+        self.drone.push_input(y);
+        let status = self.drone.run()?;
+        let pc = self.drone.pc;
+        let instruction = self.drone.memory.get(pc.max(0) as usize);
+        match status {
+            Status::Output(value) => Ok(value == 1),
+            Status::NeedsInput => Err(IntcodeError::InputClosed { pc, instruction }),
+            Status::Halted => Err(IntcodeError::HaltedWithoutOutput { pc, instruction }),
+        }
+    }
+
+    /// The first column in `from..to` that the beam pulls in row `y`. Rows close to the emitter
+    /// can be empty, as the beam is narrower than a column there.
+    fn left_edge(&mut self, y: i64, from: i64, to: i64) -> Result<Option<i64>, IntcodeError> {
+        for x in from..to {
+            if self.query(x, y)? {
+                return Ok(Some(x))
+            }
+        }
+        Ok(None)
+    }
+
+    /// Number of positions pulled by the beam with 0 <= x < width and 0 <= y < height.
+    pub fn count(&mut self, width: i64, height: i64) -> Result<usize, IntcodeError> {
+        let mut count = 0;
+        let mut left = 0;
+        let mut right = 0;
+
+        for y in 0..height {
+            let start = match self.left_edge(y, left, width)? {
+                Some(x) => x,
+                None => continue,
+            };
+            left = start;
+
+            // the right edge doesn't move left either, skip to it directly
+            let mut end = right.max(start + 1);
+            if end > start + 1 && !self.query(end - 1, y)? {
+                end = start + 1;
+            }
+            while end < width && self.query(end, y)? {
+                end += 1;
+            }
+            right = end;
+            count += (end.min(width) - start) as usize;
+        }
+
+        Ok(count)
+    }
+
+    /// Draws the beam over the region, `#` where it pulls and `.` elsewhere.
+    pub fn render(&mut self, width: i64, height: i64) -> Result<String, IntcodeError> {
+        let mut out = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                out.push(if self.query(x, y)? { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// The top left corner of the square of `size` by `size` positions, all pulled by the beam,
+    /// that is the closest to the emitter. Follows the left edge of the beam down with the bottom
+    /// left corner of the square, until its top right corner is in the beam as well. Only rows
+    /// above `rows` are searched, `None` meaning the square doesn't fit there.
+    pub fn fit(&mut self, size: i64, rows: i64) -> Result<Option<(i64, i64)>, IntcodeError> {
+        if size < 1 {
+            return Ok(None)
+        }
+
+        let mut x = 0;
+        for y in size - 1..rows {
+            // give up on rows where the beam isn't found reasonably close to where it was
+            if let Some(left) = self.left_edge(y, x, x + 10 * (y + 1))? {
+                x = left;
+                if self.query(x + size - 1, y - size + 1)? {
+                    return Ok(Some((x, y - size + 1)))
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // pulls where 0.6 y <= x <= 0.8 y
+    const DRONE: &str = "
+                in x
+                in y
+                mul x, #5, x
+                mul y, #3, low
+                mul y, #4, high
+                lt x, low, left
+                lt high, x, right
+                add left, right, left
+                eq left, #0, left
+                out left
+                halt
+        x:      db 0
+        y:      db 0
+        low:    db 0
+        high:   db 0
+        left:   db 0
+        right:  db 0
+    ";
+
+    #[test]
+    fn test_render() {
+        let mut beam = Beam::new(&assemble(DRONE).unwrap());
+        assert_eq!(beam.render(6, 7).unwrap(), "\
+#.....
+......
+......
+..#...
+...#..
+...##.
+....#.
+");
+    }
+
+    #[test]
+    fn test_count() {
+        let mut beam = Beam::new(&assemble(DRONE).unwrap());
+        for &(width, height) in &[(6, 7), (50, 50), (30, 60), (1, 1)] {
+            let mut expected = 0;
+            for y in 0..height {
+                for x in 0..width {
+                    if beam.query(x, y).unwrap() {
+                        expected += 1;
+                    }
+                }
+            }
+
+            assert_eq!(beam.count(width, height).unwrap(), expected);
+        }
+
+        // a fraction of the 2500 positions in the region
+        beam.queries = 0;
+        beam.count(50, 50).unwrap();
+        assert!(beam.queries < 500);
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut beam = Beam::new(&assemble(DRONE).unwrap());
+        let fits = |beam: &mut Beam, (x, y): (i64, i64), size: i64| {
+            (0..size).all(|dy| (0..size).all(|dx| beam.query(x + dx, y + dy).unwrap()))
+        };
+
+        for size in 1..5 {
+            let found = beam.fit(size, 100).unwrap().unwrap();
+            assert!(fits(&mut beam, found, size));
+            // nothing closer fits
+            for y in 0..found.1 {
+                assert!((0..=y).all(|x| !fits(&mut beam, (x, y), size)));
+            }
+            assert!((0..found.0).all(|x| !fits(&mut beam, (x, found.1), size)));
+        }
+
+        assert_eq!(beam.fit(0, 100), Ok(None));
+        assert_eq!(beam.fit(4, 10), Ok(None));
+    }
+
+    #[test]
+    fn test_no_report() {
+        let mut beam = Beam::new(&assemble("in x\nhalt\nx: db 0").unwrap());
+        assert_eq!(beam.query(1, 2), Err(IntcodeError::HaltedWithoutOutput { pc: 2, instruction: 99 }));
+
+        let mut beam = Beam::new(&assemble("in x\nin x\nin x\nx: db 0").unwrap());
+        assert_eq!(beam.query(1, 2), Err(IntcodeError::InputClosed { pc: 4, instruction: 3 }));
+    }
+}
@@ -1,94 +1,45 @@
-use std::collections::HashMap;
-
 use std::io::{self, Read};
 
+use ::aoc2019::intcode::{Patch, State, Sweep};
+
+/// Restores the program to the 1202 program alarm state it had just before the fire.
+const ALARM: &str = "
+    noun: 1
+    verb: 2
+    noun = 12
+    verb = 2
+";
+
+const NOUN_VERB: &str = "
+    noun: 1
+    verb: 2
+    noun = 0..=99
+    verb = 0..=99
+";
+
 fn main() -> std::io::Result<()>{
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
-    let mut program = input.split(",").map(|x| x.trim().parse().unwrap()).collect::<Vec<usize>>();
-
-    program[1] = 12;
-    program[2] = 2;
+    let program = input.split(",").map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
 
-    println!("Part 01: {}", run(&program));
+    let mut alarm = program.clone();
+    ALARM.parse::<Patch>().unwrap().apply(&mut alarm).unwrap();
+    println!("Part 01: {}", run(&alarm));
 
     let target = 19690720;
-    'l: for noun in 0..=99 {
-        for verb in 0..=99 {
-            program[1] = noun;
-            program[2] = verb;
-            if run(&program) == target {
-                println!("Part 02: {}", 100 * noun + verb);
-                break 'l;
-            }
-        }
+    let sweep = Sweep::patch(&program, &NOUN_VERB.parse().unwrap()).unwrap();
+    // a noun and verb that break the program only rule themselves out
+    if let Some(found) = sweep.first(|state| Ok(state.run_to_halt().is_ok() && state.memory.get(0) == target)).unwrap() {
+        println!("Part 02: {}", 100 * found[0] + found[1]);
     }
     Ok(())
 }
 
-#[derive(Default, Debug)]
-struct State {
-    program: HashMap<usize, usize>,
-    pc: usize,
-}
-
-impl State {
-    pub fn from(program: &[usize]) -> Self {
-        let mut state = State{pc: 0, program: HashMap::with_capacity(program.len())};
-
-        for (i, op) in program.iter().enumerate() {
-            state.program.insert(i, *op);
-        }
-
-        state
-    }
-}
-
-enum Next {
-    Continue,
-    Exit(usize),
-}
-
-fn exec_op(state: &mut State) -> Next {
-    let op = state.program.get(&state.pc).unwrap();
-    match op {
-        99 => {
-            return Next::Exit(*state.program.get(&0).unwrap_or(&0))
-        },
-        1 => {
-            let sum = 
-                state.program.get(state.program.get(&(state.pc + 1)).unwrap_or(&0)).unwrap_or(&0) +
-                state.program.get(&state.program.get(&(state.pc + 2)).unwrap_or(&0)).unwrap_or(&0);
-
-            state.program.insert(*state.program.get(&(state.pc + 3)).unwrap_or(&0), sum);
-
-            state.pc += 4;
-
-            Next::Continue
-        },
-        2 => {
-            let sum = 
-                state.program.get(&state.program.get(&(state.pc + 1)).unwrap_or(&0)).unwrap_or(&0) *
-                state.program.get(&state.program.get(&(state.pc + 2)).unwrap_or(&0)).unwrap_or(&0);
-
-            state.program.insert(*state.program.get(&(state.pc + 3)).unwrap_or(&0), sum);
-
-            state.pc += 4;
-
-            Next::Continue
-        },
-        _ => unreachable!(),
-    }
-}
-
-fn run(program: &[usize]) -> usize {
-    let mut state = State::from(program);
-    loop {
-        if let Next::Exit(value) = exec_op(&mut state) {
-            return value;
-        }
-    }
+fn run(program: &[i64]) -> i64 {
+    let mut state = State::new(program);
+    state.run_to_halt().unwrap();
+    state.memory.get(0)
 }
 
 #[cfg(test)]
@@ -69,14 +69,14 @@ mod tests {
 
     #[test]
     fn test_matches() {
-        assert!(matches(&vec![1, 1, 1, 1, 1, 1]));
+        assert!(matches(&[1, 1, 1, 1, 1, 1]));
         assert!(!matches(&digits(223450)));
         assert!(!matches(&digits(123789)));
     }
 
     #[test]
     fn test_matches02() {
-        assert!(!matches02(&vec![1, 1, 1, 1, 1, 1]));
+        assert!(!matches02(&[1, 1, 1, 1, 1, 1]));
         assert!(!matches02(&digits(123444)));
         assert!(matches02(&digits(111122)));
     }
@@ -36,7 +36,7 @@ impl World {
         let mut result = self.edges.clone();
         for (origin, destinations) in self.edges.iter() {
             for dest in destinations.iter() {
-                let entry = result.entry(dest.to_string()).or_insert_with(HashSet::new);
+                let entry = result.entry(dest.to_string()).or_default();
                 entry.insert(origin.to_string());
             }
         }
@@ -105,7 +105,7 @@ impl std::str::FromStr for World {
             let to = parts[1].to_string();
             world.nodes.insert(from.to_owned());
             world.nodes.insert(to.to_owned());
-            let entry = world.edges.entry(from).or_insert_with(HashSet::new);
+            let entry = world.edges.entry(from).or_default();
             entry.insert(to);
         }
 
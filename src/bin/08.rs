@@ -7,7 +7,7 @@ fn main() -> io::Result<()> {
     let layer_height = 6;
 
     assert_eq!(input.len() % (layer_width * layer_height), 0);
-    assert!(input.iter().all(|x| x >= &b'0' && x <= &b'9'));
+    assert!(input.iter().all(|x| (&b'0'..=&b'9').contains(&x)));
     println!("Part 01: {:?}", part1(&input, layer_width, layer_height));
 
     println!("Part 02");
@@ -16,7 +16,7 @@ fn main() -> io::Result<()> {
 }
 
 fn part1(input: &[u8], layer_width: usize, layer_height: usize) -> Option<usize> {
-    let (min_zero_layer, _zeroes) = input.chunks(layer_width * layer_height).fold((None, usize::max_value()), |acc, layer| {
+    let (min_zero_layer, _zeroes) = input.chunks(layer_width * layer_height).fold((None, usize::MAX), |acc, layer| {
         let zeroes = layer.iter().filter(|el| **el == b'0').count();
         if zeroes < acc.1 {
             (Some(layer), zeroes)
@@ -25,11 +25,7 @@ fn part1(input: &[u8], layer_width: usize, layer_height: usize) -> Option<usize>
         }
     });
 
-    if let Some(layer) = min_zero_layer {
-        Some(layer.iter().filter(|el| **el == b'1').count() * layer.iter().filter(|el| **el == b'2').count())
-    } else {
-        None
-    }
+    min_zero_layer.map(|layer| layer.iter().filter(|el| **el == b'1').count() * layer.iter().filter(|el| **el == b'2').count())
 }
 
 fn part2(input: &[u8], layer_width: usize, layer_height: usize) {
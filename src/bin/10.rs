@@ -54,13 +54,10 @@ impl<'a> Iterator for Positions<'a> {
 }
 
 impl World {
-    fn meteors(&self) -> Positions {
+    fn meteors(&self) -> Positions<'_> {
         Positions{world: self, x: 0, y: 0, filter: Some(Cell::Meteor)}
     }
 
-    fn emptys(&self) -> Positions {
-        Positions{world: self, x: 0, y: 0, filter: Some(Cell::Empty)}
-    }
 }
 
 use std::fmt;
@@ -71,7 +68,7 @@ impl fmt::Display for World {
             for x in 0..self.w {
                 write!(f, "{}", self.cells[y*self.w + x])?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -89,7 +86,7 @@ impl FromStr for World {
         let mut cells = Vec::with_capacity(input.len());
         for line in input.lines() {
             let trimmed = line.trim();
-            if trimmed.len() == 0 { continue }
+            if trimmed.is_empty() { continue }
             h += 1;
             w = trimmed.len();
 
@@ -193,14 +190,14 @@ fn part2(world: &World, station: (usize, usize)) ->  usize {
     let mut destroyed = 0;
     let mut i = 0;
     let mut last_destroyed = (0, 0);
-    let mut turned = false;
+    let mut turned;
 
     while destroyed < 200  {
         last_destroyed = meteors.remove(i);
         destroyed += 1;
         turned = false;
 
-        if meteors.len() == 0 {
+        if meteors.is_empty() {
             break
         }
 
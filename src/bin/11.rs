@@ -1,8 +1,8 @@
 use std::collections::{HashMap, HashSet};
 
 use std::io::{self, Read};
-use std::sync::mpsc::{sync_channel, SyncSender, Receiver, RecvError, TryRecvError};
-use std::thread;
+
+use ::aoc2019::intcode::{State, Status};
 
 fn main() -> io::Result<()> {
     let mut input = String::new();
@@ -64,23 +64,18 @@ fn draw(painted: &HashMap<(i64, i64), i64>) {
 
     for y in 0..height {
         for x in 0..width {
-            if painted.contains(&(x as i64, y as i64)) {
+            if painted.contains(&(x, y)) {
                 print!("*");
             } else {
                 print!(" ");
             }
         }
-        println!("");
+        println!();
     }
 }
 
 fn run(program: &[i64], initial_color: i64) -> (HashMap<(i64, i64), i64>, usize) {
-    let (inputs_sender, inputs_receiver) = sync_channel(0);
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = State::from(0, program, inputs_receiver, outputs_sender);
-    thread::spawn(move || {
-        while let Next::Continue = exec_op(&mut state) {}
-    });
+    let mut state = State::new(program);
 
     let mut painted = HashMap::new();
     painted.insert((0, 0), initial_color);
@@ -88,210 +83,25 @@ fn run(program: &[i64], initial_color: i64) -> (HashMap<(i64, i64), i64>, usize)
     let mut dir = Dir::North;
 
     loop {
-        if let Ok(()) = inputs_sender.send(*painted.get(&pos).unwrap_or(&0)) {
-        }
-
-        match outputs_receiver.recv() {
-            Ok(color) => {
-                painted.insert(pos.clone(), color);
-                let rotation = outputs_receiver.recv().unwrap();
+        match state.run().unwrap() {
+            Status::NeedsInput => state.push_input(*painted.get(&pos).unwrap_or(&0)),
+            Status::Output(color) => {
+                painted.insert(pos, color);
+                let rotation = match state.run().unwrap() {
+                    Status::Output(rotation) => rotation,
+                    status => panic!("expected a rotation, got {:?}", status),
+                };
                 dir = dir.rotate(rotation);
                 dir.advance(&mut pos);
             },
-            Err(RecvError) => break,
-            _ => {}
+            Status::Halted => break,
         };
-
     }
     let l = painted.len();
 
     (painted, l)
 }
 
-#[derive(Debug)]
-struct State {
-    id: usize,
-    inputs: Receiver<i64>,
-    outputs: SyncSender<i64>,
-    program: HashMap<i64, i64>,
-    pc: i64,
-    relative_base: i64,
-}
-
-impl State {
-    pub fn from(id: usize, program: &[i64], inputs: Receiver<i64>, outputs: SyncSender<i64>) -> Self {
-        let mut state = State{id, inputs , outputs, pc: 0, program: HashMap::with_capacity(program.len()), relative_base: 0};
-
-        for (i, op) in program.iter().enumerate() {
-            state.program.insert(i as i64, *op);
-        }
-
-        state
-    }
-}
-
-#[derive(Debug, PartialEq, Eq)]
-enum Next {
-    Continue,
-    Exit(Option<i64>),
-}
-
-const POSITION: i64 = 0;
-const IMMEDIATE: i64 = 1;
-const RELATIVE: i64 = 2;
-
-fn addr_from_param(state: &State, op:i64, param_position: i64) -> i64 {
-    let addr = state.pc + param_position;
-    let param = *state.program.get(&addr).expect(&format!("Expected value at position: {}, got none", addr));
-    let diviser = match param_position {
-        1 => 100,
-        2 => 1000,
-        3 => 10000,
-        _ => unreachable!()
-    };
-    let mode = (op / diviser) % 10;
-
-    match mode {
-        POSITION => {
-            param
-        },
-        RELATIVE => {
-            param + state.relative_base
-        },
-        _ => unreachable!()
-    }
-}
-
-fn value_from_param(state: &State, op:i64, param_position: i64) -> i64 {
-    let addr = state.pc + param_position;
-    let param = *state.program.get(&addr).expect(&format!("Expected value at position: {}, got none", addr));
-    let diviser = match param_position {
-        1 => 100,
-        2 => 1000,
-        3 => 10000,
-        _ => unreachable!()
-    };
-    let mode = (op / diviser) % 10;
-
-    match mode {
-        IMMEDIATE => param,
-        POSITION => {
-            //*state.program.get(&param).expect(&format!("Expected to get value from {} at {}", param, state.pc))
-            *state.program.get(&param).unwrap_or(&0)
-        },
-        RELATIVE => {
-            //*state.program.get(&(param + state.relative_base)).expect(&format!("Expected to get value from {} ({} + {}), got none", param + state.relative_base, param, state.relative_base))
-            *state.program.get(&(param + state.relative_base)).unwrap_or(&0)
-        },
-        _ => unreachable!()
-    }
-}
-
-fn exec_op(state: &mut State) -> Next {
-    let op = state.program.get(&state.pc).unwrap();
-    match op % 100 {
-        99 => {
-            return Next::Exit(None)
-        },
-        1 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
-
-            let sum = first_value + second_value;
-            state.program.insert(addr, sum);
-
-            state.pc += 4;
-
-            Next::Continue
-        },
-        2 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
-
-            let product = first_value * second_value;
-            state.program.insert(addr, product);
-            state.pc += 4;
-
-            Next::Continue
-        },
-        3 => {
-            let addr = addr_from_param(&state, *op, 1);
-            let value = state.inputs.recv().unwrap();
-            //println!("[{}] Got a {} from the input channel", state.id, value);
-            state.program.insert(addr, value);
-            state.pc += 2;
-            Next::Continue
-        },
-        4 => {
-            let value = value_from_param(&state, *op, 1);
-
-            if let Err(_) = state.outputs.send(value) {
-                // receiver has dropped, it's time to bail out
-                return Next::Exit(Some(value))
-            }
-            state.pc += 2;
-            Next::Continue
-        },
-        5 => {
-            let value = value_from_param(&state, *op, 1);
-
-            if value != 0 {
-                let value = value_from_param(&state, *op, 2);
-                state.pc = value;
-            } else {
-                state.pc += 3;
-            }
-            Next::Continue
-        },
-        6 => {
-            let value = value_from_param(&state, *op, 1);
-
-            if value == 0 {
-                let value = value_from_param(&state, *op, 2);
-                state.pc = value;
-            } else {
-                state.pc += 3;
-            }
-            Next::Continue
-        },
-        7 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
-
-            if first_value < second_value {
-                state.program.insert(addr, 1);
-            } else {
-                state.program.insert(addr, 0);
-            }
-            state.pc += 4;
-            Next::Continue
-        },
-        8 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
-
-            if first_value == second_value {
-                state.program.insert(addr, 1);
-            } else {
-                state.program.insert(addr, 0);
-            }
-            state.pc += 4;
-            Next::Continue
-        },
-        9 => {
-            let value = value_from_param(&state, *op, 1);
-            state.relative_base += value;
-            state.pc += 2;
-            Next::Continue
-        }
-        _ => unreachable!(),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,8 +109,11 @@ mod tests {
 
     #[test]
     fn example_part1() {
-        let program: &[i64] = &[104,1125899906842624,99][..];
-        assert_eq!(run(program, 1), 1125899906842624);
+        // paints the starting panel white and turns left
+        let program: &[i64] = &[3,100,104,1,104,0,99][..];
+        let (painted, count) = run(program, 0);
+        assert_eq!(count, 1);
+        assert_eq!(painted.get(&(0, 0)), Some(&1));
     }
 
     #[test]
@@ -1,4 +1,3 @@
-use num;
 use std::io::{self, BufRead};
 fn main() -> io::Result<()> {
     let mut planets = io::stdin().lock().lines().map(|line| {
@@ -11,7 +10,7 @@ fn main() -> io::Result<()> {
     println!("Part 01: {}", planets.iter().map(|planet| planet.energy()).sum::<i64>());
 
     // Part 02
-    let lengths = cycle_length(&mut planets);
+    let lengths = cycle_length(&planets);
     let cycle = num::integer::lcm((lengths.0).1, num::integer::lcm((lengths.1).1, (lengths.2).1));
     println!("Part 02: {}", cycle);
 
@@ -29,7 +28,7 @@ use std::collections::HashMap;
 fn cycle_length(planets: &[Planet]) -> ((usize, usize), (usize, usize), (usize, usize)) {
     let mut result = ((0, 0), (0, 0), (0, 0));
     // X
-    let mut pls = planets.iter().cloned().collect::<Vec<Planet>>();
+    let mut pls = planets.to_vec();
     let mut seen = HashMap::new();
     let mut i = 0;
     loop {
@@ -47,7 +46,7 @@ fn cycle_length(planets: &[Planet]) -> ((usize, usize), (usize, usize), (usize,
     }
 
     // Y
-    let mut pls = planets.iter().cloned().collect::<Vec<Planet>>();
+    let mut pls = planets.to_vec();
     let mut seen = HashMap::new();
     let mut i = 0;
     loop {
@@ -65,7 +64,7 @@ fn cycle_length(planets: &[Planet]) -> ((usize, usize), (usize, usize), (usize,
     }
 
     // Y
-    let mut pls = planets.iter().cloned().collect::<Vec<Planet>>();
+    let mut pls = planets.to_vec();
     let mut seen = HashMap::new();
     let mut i = 0;
     loop {
@@ -91,7 +90,7 @@ fn step(planets: &mut Vec<Planet>) {
     apply_velocity(planets);
 }
 
-fn apply_gravity(planets: &mut Vec<Planet>) {
+fn apply_gravity(planets: &mut [Planet]) {
     let l = planets.len();
     for i in 0..l {
         for j in (i+1)..l {
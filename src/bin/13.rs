@@ -1,276 +1,192 @@
-use std::io::{self, Read};
+//! Plays the arcade game, with the program read from stdin:
+//!
+//!     cargo run --bin 13 < inputs/13.txt
+//!     cargo run --bin 13 -- --ai 20 < inputs/13.txt
+//!     cargo run --bin 13 -- --play 150 < inputs/13.txt
+//!     cargo run --bin 13 -- --play 150 --record game.txt < inputs/13.txt
+//!     cargo run --bin 13 -- --replay game.txt [ms] < inputs/13.txt
+//!     cargo run --bin 13 -- --solve 10000 --record game.txt < inputs/13.txt
+//!     cargo run --bin 13 -- --beam 16 --record game.txt < inputs/13.txt
+//!
+//! Without arguments, prints the answers without drawing anything. `--ai` draws the game on the
+//! terminal as the paddle follows the ball, waiting the given number of milliseconds between
+//! frames. `--play` lets the arrow keys move the paddle, `q` quitting. `--record` saves the game
+//! played in any of these modes as a replay. `--replay` plays a replay back, drawing it if given
+//! a delay, and checks it ends with the recorded score and blocks. `--solve` searches for the
+//! game won with the fewest joystick moves, giving up after forking the game that many times, and
+//! compares it to following the ball. The number of games grows quickly with every bounce, so
+//! `--beam` only keeps that many of them at every bounce: wider searches tend to find shorter
+//! games, but none is guaranteed to find the shortest.
+
+use std::env;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::channel;
 use std::thread;
-use std::sync::mpsc::{sync_channel, TryRecvError};
+use std::time::Duration;
 
-use ::aoc2019::intcode;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::{clear, cursor};
+
+use ::aoc2019::arcade::{beam_search, search, Arcade, Replay};
 
 fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let record = match args.iter().position(|arg| arg == "--record") {
+        Some(i) if i + 1 < args.len() => Some(args.drain(i..i + 2).nth(1).unwrap()),
+        _ => None,
+    };
+    let delay = |arg: Option<&String>, default| Duration::from_millis(arg.map(|ms| ms.parse().unwrap()).unwrap_or(default));
+
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
-
-    let mut program = input.split(",").map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
-    println!("Part 01: {}", run(&program).0);
-
-    program[0] = 2;
-
-    println!("{:?}", run_with_joystick(&program));
-    Ok(())
-}
-
-fn run_with_joystick(program: &[i64]) -> i64 {
-    let mut screen = HashMap::new();
-    let mut score = 0;
-
-    let (inputs_sender, inputs_receiver) = sync_channel(0);
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
-
-    thread::spawn(move || {
-        while let intcode::Next::Continue = intcode::exec_op(&mut state) {}
-    });
-
-
-    loop {
-        let next_input = match screen.iter().find(|(_, tile)| tile == &&Tile::Ball) {
-            Some(((ball_x, _), _)) => {
-                match screen.iter().find(|(_, tile)| tile == &&Tile::Paddle) {
-                    Some(((paddle_x, _), _)) => {
-                        if ball_x > paddle_x {
-                            1
-                        } else if ball_x <  paddle_x {
-                            -1
-                        } else {
-                            0
-                        }
-                    },
-                    None => 0
-                }
-
-            }
-            None => 0,
-        };
-
-        inputs_sender.try_send(next_input);
-
-        match outputs_receiver.try_recv() {
-            Ok(x) => {
-                let y = outputs_receiver.recv().unwrap();
-                let tile_type = outputs_receiver.recv().unwrap();
-
-                if x == -1 && y == 0 {
-                    score = tile_type;
-                } else {
-                    if tile_type == 0 {
-                        screen.remove(&(x, y));
-                    } else {
-                        screen.insert((x, y), match tile_type {
-                            1 => Tile::Wall,
-                            2 => Tile::Block,
-                            3 => Tile::Paddle,
-                            4 => Tile::Ball,
-                            _ => unreachable!()
-                        });
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+
+    let arcade = match args.first().map(|arg| arg.as_str()) {
+        None | Some("--headless") => {
+            let mut arcade = Arcade::new(&program);
+            arcade.update().unwrap();
+            println!("Part 01: {}", arcade.blocks());
+            let mut arcade = Arcade::free_play(&program);
+            println!("{:?}", arcade.play(Arcade::follow).unwrap());
+            arcade
+        },
+        Some("--ai") => {
+            let delay = delay(args.get(1), 20);
+            let mut out = io::stdout();
+            write!(out, "{}{}", clear::All, cursor::Hide)?;
+            let mut arcade = Arcade::free_play(&program);
+            let score = arcade.play(|arcade| {
+                draw(&mut out, arcade).unwrap();
+                thread::sleep(delay);
+                arcade.follow()
+            }).unwrap();
+            draw(&mut out, &arcade)?;
+            write!(out, "{}", cursor::Show)?;
+            println!("Final score: {}", score);
+            arcade
+        },
+        Some("--play") => {
+            let delay = delay(args.get(1), 150);
+            let arcade = play(&program, delay)?;
+            println!("Final score: {}", arcade.score);
+            arcade
+        },
+        Some(mode @ "--solve") | Some(mode @ "--beam") => {
+            let mut greedy = Arcade::free_play(&program);
+            greedy.play(Arcade::follow).unwrap();
+            println!("Following the ball: score {}, {} moves, {} steps", greedy.score, greedy.moves.len(), greedy.state.steps);
+
+            let found = match mode {
+                "--solve" => search(&program, args.get(1).map(|max| max.parse().unwrap()).unwrap_or(10_000)),
+                _ => beam_search(&program, args.get(1).map(|width| width.parse().unwrap()).unwrap_or(16)),
+            };
+            let solution = match found {
+                Ok(Some(solution)) => solution,
+                Ok(None) => {
+                    eprintln!("No winning game found");
+                    std::process::exit(1);
+                },
+                Err(error) => {
+                    eprintln!("Search failed: {}", error);
+                    std::process::exit(1);
+                },
+            };
+            println!("Search: score {}, {} moves, {} steps over {} branches",
+                solution.replay.ending.score, solution.replay.moves.len(), solution.steps, solution.branches);
+            solution.replay.play(&program).unwrap()
+        },
+        Some("--replay") if args.len() >= 2 => {
+            let replay = Replay::load(&args[1])?;
+            let arcade = match args.get(2) {
+                Some(_) => {
+                    let delay = delay(args.get(2), 0);
+                    let mut out = io::stdout();
+                    write!(out, "{}{}", clear::All, cursor::Hide)?;
+                    let mut arcade = Arcade::free_play(&program);
+                    arcade.update().unwrap();
+                    while !arcade.is_halted() && arcade.frame < replay.ending.frames {
+                        draw(&mut out, &arcade)?;
+                        thread::sleep(delay);
+                        arcade.joystick(replay.position(arcade.frame)).unwrap();
                     }
-                }
+                    draw(&mut out, &arcade)?;
+                    write!(out, "{}", cursor::Show)?;
+                    arcade
+                },
+                None => replay.play(&program).unwrap(),
+            };
+
+            match replay.check(&arcade) {
+                Ok(()) => println!("Replay verified: {}", replay.ending),
+                Err(error) => {
+                    eprintln!("Replay diverged: {}", error);
+                    std::process::exit(1);
+                },
             }
-            Err(TryRecvError::Disconnected) => {
-                break;
-            },
-            _ => {}
-        }
+            arcade
+        },
+        _ => {
+            eprintln!("usage: 13 [--headless | --ai [ms] | --play [ms] | --replay <file> [ms] | --solve [max-branches] | --beam [width]] [--record <file>] < program");
+            std::process::exit(1);
+        },
+    };
+
+    if let Some(path) = record {
+        arcade.replay().save(path)?;
     }
 
-    score
+    Ok(())
 }
 
-//use std::collections::BinaryHeap;
-//fn high_score(program: &[i64]) -> i64 {
-    //let mut attempts = BinaryHeap::new();
-
-    //attempts.push(Attempt{init: Vec::new(), next_input: 0, score: 0, blocks_left: 0, inputs_entered: 0, previous_attempt: 0, high_score: 0});
-
-    //let mut attempts_seen = 0;
-    //let mut max_high_score = 0;
-
-    //while let Some(mut attempt) = attempts.pop() {
-        //attempt.run(program);
-        //attempts_seen += 1;
-
-        //if attempt.high_score > max_high_score {
-            //max_high_score = attempt.high_score;
-            //println!("Attempt #{} reached: {}", attempts_seen, max_high_score);
-        //}
-
-        //if attempt.blocks_left == 0 {
-            //return attempt.score
-        //}
-
-        //if attempt.inputs_entered <= attempt.init.len() {
-            //continue
-        //}
-
-        //for next_attempt in attempt.new_attempts() {
-            //attempts.push(next_attempt);
-        //}
-    //}
-
-    //0
-//}
-
-//#[derive(Debug, Eq, PartialEq, Clone)]
-//struct Attempt {
-    //init: Vec<i64>,
-    //next_input: i64,
-    //score: i64,
-    //high_score: i64,
-    //blocks_left: usize,
-    //inputs_entered: usize,
-    //previous_attempt: usize,
-//}
-
-//impl Attempt {
-    //fn new_attempts(&self) -> Vec<Attempt> {
-        //let mut results = Vec::with_capacity(3);
-        //let mut new = self.clone();
-        //new.previous_attempt = self.inputs_entered;
-        ////for i in 0..(self.inputs_entered - self.init.len()) {
-        ////}
-        //let mut left = new.clone();
-        //left.next_input = 0;
-        //left.init.push(-1);
-        //results.push(left);
-
-        //let mut stay = new.clone();
-        //stay.init.push(0);
-        //stay.next_input = 0;
-        //results.push(stay);
-
-        //let mut right = new.clone();
-        //right.next_input = 0;
-        //right.init.push(1);
-        //results.push(right);
-
-        //results
-    //}
-
-    //fn run(&mut self, program: &[i64]) {
-        //let mut screen = HashMap::new();
-        //self.score = 0;
-        //self.high_score = 0;
-        //self.inputs_entered = 0;
-
-        //let (inputs_sender, inputs_receiver) = sync_channel(0);
-        //let (outputs_sender, outputs_receiver) = sync_channel(0);
-        //let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
-
-        //thread::spawn(move || {
-            //while let intcode::Next::Continue = intcode::exec_op(&mut state) {}
-        //});
-
-
-        //loop {
-            //let next_input = if self.inputs_entered >= self.init.len() {
-                //self.next_input
-            //} else {
-                //self.init[self.inputs_entered]
-            //};
-
-            //if inputs_sender.try_send(next_input).is_ok() {
-                //self.inputs_entered += 1;
-            //}
-
-            //match outputs_receiver.try_recv() {
-                //Ok(x) => {
-                    //let y = outputs_receiver.recv().unwrap();
-                    //let tile_type = outputs_receiver.recv().unwrap();
-
-                    //if x == -1 && y == 0 {
-                        //self.score = tile_type;
-                        //if self.score > self.high_score {
-                            //self.high_score = self.score;
-                        //}
-                        //continue
-                    //}
-
-                    //if tile_type == 0 {
-                        //screen.remove(&(x, y));
-                    //} else {
-                        //screen.insert((x, y), match tile_type {
-                            //1 => Tile::Wall,
-                            //2 => Tile::Block,
-                            //3 => Tile::Paddle,
-                            //4 => Tile::Ball,
-                            //_ => unreachable!()
-                        //});
-                    //}
-                //}
-                //Err(TryRecvError::Disconnected) => {
-                    //break;
-                //},
-                //_ => {}
-            //}
-        //}
-
-        //self.blocks_left = screen.iter().filter(|(_, tile)| tile == &&Tile::Block).count();
-    //}
-//}
-
-//use std::cmp::{Ord, Ordering};
-//impl Ord for Attempt {
-    //fn cmp(&self, other: &Attempt) -> Ordering {
-        //self.high_score.cmp(&other.high_score).then_with(|| other.blocks_left.cmp(&self.blocks_left)).then_with(|| self.previous_attempt.cmp(&other.previous_attempt)).then_with(|| other.init.cmp(&self.init))
-    //}
-//}
-
-//impl PartialOrd for Attempt {
-    //fn partial_cmp(&self, other: &Attempt) -> Option<Ordering> {
-        //Some(self.cmp(&other))
-    //}
-
-//}
-
-use std::collections::HashMap;
-fn run(program: &[i64]) -> (usize, i64) {
-    let mut screen = HashMap::new();
-    let mut score = 0;
-
-    let (_inputs_sender, inputs_receiver) = sync_channel(0);
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
-
+/// Lets the player move the paddle with the arrow keys, read from the terminal since stdin holds
+/// the program. The game doesn't wait for the player: the joystick stays in the middle on frames
+/// without a key press.
+fn play(program: &[i64], delay: Duration) -> io::Result<Arcade> {
+    let (keys, presses) = channel();
+    let tty = termion::get_tty()?;
     thread::spawn(move || {
-        while let intcode::Next::Continue = intcode::exec_op(&mut state) {}
-    });
-
-    while let Ok(x) = outputs_receiver.recv() {
-        let y = outputs_receiver.recv().unwrap();
-        let tile_type = outputs_receiver.recv().unwrap();
-
-        if x == -1 && y == 0 {
-            score = tile_type;
-            continue
+        for key in tty.keys() {
+            if keys.send(key).is_err() {
+                break
+            }
         }
+    });
 
-        if tile_type == 0 {
-            screen.remove(&(x, y));
-        } else {
-            screen.insert((x, y), match tile_type {
-                1 => Tile::Wall,
-                2 => Tile::Block,
-                3 => Tile::Paddle,
-                4 => Tile::Ball,
-                _ => unreachable!()
-            });
+    let mut out = io::stdout().into_raw_mode()?;
+    write!(out, "{}{}", clear::All, cursor::Hide)?;
+
+    let mut arcade = Arcade::free_play(program);
+    arcade.update().unwrap();
+    while !arcade.is_halted() {
+        draw(&mut out, &arcade)?;
+        thread::sleep(delay);
+
+        let mut position = 0;
+        for key in presses.try_iter() {
+            match key? {
+                Key::Left => position = -1,
+                Key::Right => position = 1,
+                Key::Char('q') | Key::Ctrl('c') => {
+                    write!(out, "{}\r\n", cursor::Show)?;
+                    return Ok(arcade)
+                },
+                _ => {},
+            }
         }
+        arcade.joystick(position).unwrap();
     }
 
-    (screen.iter().filter(|(_, tile)| tile == &&Tile::Block).count(), score)
+    draw(&mut out, &arcade)?;
+    write!(out, "{}\r\n", cursor::Show)?;
+    Ok(arcade)
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum Tile {
-    Wall,
-    Block,
-    Paddle,
-    Ball
+fn draw<W: Write>(out: &mut W, arcade: &Arcade) -> io::Result<()> {
+    write!(out, "{}", cursor::Goto(1, 1))?;
+    for line in arcade.render().lines() {
+        write!(out, "{}{}\r\n", line, clear::UntilNewline)?;
+    }
+    out.flush()
 }
@@ -16,7 +16,7 @@ fn bsearch(reactions: &[Reaction], limit: usize, lower: usize, upper: usize) ->
     let mut upper = upper;
     while lower < upper - 1 {
         let pivot = (lower + upper) / 2;
-        let req = ore_required(&reactions, Item::new(pivot, "FUEL"), "ORE");
+        let req = ore_required(reactions, Item::new(pivot, "FUEL"), "ORE");
         println!("lower: {}, upper: {}, pivot: {}, req: {}", lower, upper, pivot, req);
         if req == limit {
             return pivot
@@ -30,57 +30,6 @@ fn bsearch(reactions: &[Reaction], limit: usize, lower: usize, upper: usize) ->
     lower
 }
 
-fn fuel_produced(reactions: &[Reaction], goal: &str, base_item: &str) -> usize {
-    let mut inventory: HashMap<String, usize> = HashMap::new();
-    inventory.insert(base_item.to_string(), 1000000000000);
-    let mut required = Vec::new();
-    let mut produced = 0;
-
-    loop {
-        required.push(Item::new(1, goal));
-        while let Some(req) = required.pop() {
-            let mut current_stock = inventory.entry(req.name.clone()).or_insert(0);
-
-            if *current_stock >= req.quantity {
-                *current_stock -= req.quantity;
-                continue
-            }
-
-            let qty_required = req.quantity - *current_stock;
-            *current_stock = 0;
-
-            let reaction = match reactions.iter().find(|reac| reac.output.name == req.name) {
-                Some(reaction) => reaction,
-                None => {
-                    return produced
-                }
-            };
-
-
-            let multiplier = if qty_required % reaction.output.quantity == 0 {
-                qty_required / reaction.output.quantity
-            } else {
-                qty_required / reaction.output.quantity + 1
-            };
-
-            let total_produced = multiplier * reaction.output.quantity;
-
-            *current_stock +=  total_produced - qty_required;
-
-            for input in reaction.inputs.iter() {
-                let qty_required = multiplier * input.quantity;
-                required.push(Item::new(qty_required, &input.name));
-            }
-        }
-
-        produced += 1;
-        if produced % 1000 == 0 {
-            println!("{} fuel produced, {} ore left", produced, inventory.get(base_item).unwrap_or(&0));
-        }
-    }
-
-    0
-}
 
 fn ore_required(reactions: &[Reaction], goal: Item, base_item: &str) -> usize {
     let mut inventory: HashMap<String, usize> = HashMap::new();
@@ -88,7 +37,7 @@ fn ore_required(reactions: &[Reaction], goal: Item, base_item: &str) -> usize {
     required.push(goal);
 
     while let Some(req) = required.pop() {
-        let mut current_stock = inventory.entry(req.name.clone()).or_insert(0);
+        let current_stock = inventory.entry(req.name.clone()).or_insert(0);
 
         if req.name == base_item {
             *current_stock += req.quantity;
@@ -106,7 +55,7 @@ fn ore_required(reactions: &[Reaction], goal: Item, base_item: &str) -> usize {
         let reaction = reactions.iter().find(|reac| reac.output.name == req.name).unwrap();
 
 
-        let multiplier = if qty_required % reaction.output.quantity == 0 {
+        let multiplier = if qty_required.is_multiple_of(reaction.output.quantity) {
             qty_required / reaction.output.quantity
         } else {
             qty_required / reaction.output.quantity + 1
@@ -190,7 +139,7 @@ mod tests {
 7 A, 1 C => 1 D
 7 A, 1 D => 1 E
 7 A, 1 E => 1 FUEL"#).unwrap();
-        assert_eq!(ore_required(&input, "FUEL", "ORE"), 31);
+        assert_eq!(ore_required(&input, Item::new(1, "FUEL"), "ORE"), 31);
 
     }
 }
@@ -1,7 +1,5 @@
 use std::io::{self, Read};
-use std::thread;
-use std::sync::mpsc::{sync_channel, TryRecvError};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use ::aoc2019::intcode;
 
@@ -9,7 +7,7 @@ fn main() -> io::Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
-    let mut program = input.split(",").map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let program = input.split(",").map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
 
     let (mut world, path) = run_droid(&program);
     println!("Part 01: {:?}", path.unwrap().len() - 1);
@@ -34,24 +32,6 @@ impl Dir {
             Dir::East => 4,
         }
     }
-
-    fn advance(&self, pos: &mut Addr) {
-        match *self {
-            Dir::North => pos.y -= 1,
-            Dir::East => pos.x += 1,
-            Dir::South => pos.y += 1,
-            Dir::West => pos.x -= 1,
-        };
-    }
-
-    fn inverse(&self) -> Self {
-        match *self {
-            Dir::North => Dir::South,
-            Dir::South => Dir::North,
-            Dir::West => Dir::East,
-            Dir::East => Dir::West,
-        }
-    }
 }
 
 impl From<i64> for Dir {
@@ -120,46 +100,28 @@ impl Addr {
 #[derive(Debug)]
 struct World {
     tiles: HashMap<Addr, Tile>,
-    movements: Vec<Dir>,
     droid: Addr,
     oxygen: Option<Addr>,
-    backtracking: bool,
 }
 
 use pathfinding::prelude::{astar,absdiff};
 
 impl World {
     fn new() -> Self {
-        let mut w = World{tiles: HashMap::new(), droid: Addr{x: 0, y: 0}, movements: Vec::new(), backtracking: false, oxygen: None};
+        let mut w = World{tiles: HashMap::new(), droid: Addr{x: 0, y: 0}, oxygen: None};
         w.tiles.insert(Addr{x: 0, y: 0}, Tile::Empty);
         w
     }
-    fn update(&mut self, command_sent: Dir, status: Status) {
-        if status == Status::BlockedByWall {
-            let mut wall_position = self.droid.clone();
-            command_sent.advance(&mut wall_position);
-            self.tiles.insert(wall_position, Tile::Wall);
-        } else {
-            command_sent.advance(&mut self.droid);
-            if !self.backtracking {
-                self.movements.push(command_sent);
-            } else {
-                self.backtracking = false;
-            }
-            if status == Status::MovedOnOxy {
-                self.oxygen = Some(self.droid.clone());
-            }
-            self.tiles.insert(self.droid.clone(), if status == Status::Moved { Tile::Empty } else { Tile::Oxygen });
-        }
-    }
 
-    fn backtrack(&mut self) -> Option<Dir> {
-        self.backtracking = true;
-        self.movements.pop().map(|x| x.inverse())
-    }
-
-    fn hidden_neighbours(&self) -> Vec<(Dir, Addr)> {
-        self.droid.neighbours().iter().filter(|(_, n)| self.tiles.get(&n).is_none()).cloned().collect()
+    fn update(&mut self, pos: &Addr, status: Status) {
+        if status == Status::MovedOnOxy {
+            self.oxygen = Some(pos.clone());
+        }
+        self.tiles.insert(pos.clone(), match status {
+            Status::BlockedByWall => Tile::Wall,
+            Status::Moved => Tile::Empty,
+            Status::MovedOnOxy => Tile::Oxygen,
+        });
     }
 
     fn path_between(&self, origin: &Addr, dest: &Addr) -> Option<Vec<Addr>> {
@@ -176,7 +138,7 @@ impl World {
             // get all their empty neighbours
             let propagating_cells = oxygen_cells.iter().flat_map(|pos| pos.neighbours().into_iter().filter_map(|(_, n)| self.tiles.get(&n).filter(|t| **t == Tile::Empty).map(|_| n.clone()))).collect::<HashSet<_>>();
 
-            if propagating_cells.len() == 0 {
+            if propagating_cells.is_empty() {
                 break
             }
 
@@ -204,8 +166,8 @@ impl Display for World {
 
         for y in 0..height {
             for x in 0..width {
-                let adjusted_x = x as i64 + min_x;
-                let adjusted_y = y as i64 + min_y;
+                let adjusted_x = x + min_x;
+                let adjusted_y = y + min_y;
                 if self.droid.x == adjusted_x && self.droid.y == adjusted_y {
                     write!(f,"D")?;
                 } else {
@@ -218,7 +180,7 @@ impl Display for World {
 
                 }
             }
-            write!(f,"\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -237,41 +199,32 @@ impl From<i64> for Status {
     }
 }
 
-use termion::input::TermRead;
 fn run_droid(program: &[i64]) -> (World, Option<Vec<Addr>>) {
-    let (inputs_sender, inputs_receiver) = sync_channel(0);
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
     let mut world = World::new();
 
-    thread::spawn(move || state.run());
-
-    let mut step = 0;
-    loop {
-        let mut potential = world.hidden_neighbours();
-        let next_command = match potential.pop() {
-            Some((command, _)) => {
-                command
-            },
-            None => {
-                // Backtrack
-                match world.backtrack() {
-                    Some(x) => x,
-                    None => break
+    // explore breadth first, forking the droid at every cell instead of walking it back
+    let mut to_visit = VecDeque::new();
+    to_visit.push_back((Addr{x: 0, y: 0}, intcode::State::new(program)));
+
+    while let Some((pos, droid)) = to_visit.pop_front() {
+        for (dir, next) in pos.neighbours() {
+            if world.tiles.contains_key(&next) {
+                continue
+            }
+
+            let mut droid = droid.fork();
+            droid.push_input(dir.command());
+
+            if let intcode::Status::Output(status_code) = droid.run().unwrap() {
+                let status = status_code.into();
+                world.update(&next, status);
+                if status != Status::BlockedByWall {
+                    to_visit.push_back((next, droid));
                 }
             }
-        };
-        inputs_sender.send(next_command.command());
-
-        match outputs_receiver.recv() {
-            Ok(status_code) => {
-                world.update(next_command, status_code.into());
-            },
-            Err(_) => break
         }
     }
 
-    world.droid = Addr{x: 0, y: 0};
     println!("{}", world);
     println!("Oxygen is at {:?}", world.oxygen);
     let oxygen = world.oxygen.as_ref().unwrap().clone();
@@ -1,12 +1,12 @@
 use std::io::{self, Read};
 fn main() -> io::Result<()> {
     let mut input = String::new();
-    io::stdin().read_to_string(&mut input);
+    io::stdin().read_to_string(&mut input)?;
     let digits = input.trim().chars().map(|ch| ch.to_digit(10).unwrap() as u8).collect::<Vec<u8>>();
     println!("Part 01: {}", to_number(&apply_phases(&digits, 100)[0..8]));
 
     let offset = to_number(&digits[0..7]);
-    let digits = (0..10000).flat_map(|_| digits.clone()).skip((offset - 1) as usize).collect::<Vec<u8>>();
+    let digits = (0..10000).flat_map(|_| digits.clone()).skip(offset as usize).collect::<Vec<u8>>();
     println!("offset: {}", offset);
     println!("Part 02: {}", solve_at_offset(&digits, 100, 8));
     Ok(())
@@ -21,7 +21,6 @@ fn apply_phases(input: &[u8], n: usize) -> Vec<u8> {
     (0..n).fold(input.to_vec(), |acc, _| apply_phase(&pattern, &acc))
 }
 
-use itertools;
 fn apply_phase(base_pattern: &[i8], input: &[u8]) -> Vec<u8> {
     let mut out = Vec::with_capacity(input.len());
 
@@ -41,7 +40,7 @@ fn solve_at_offset(input: &[u8], iters: usize, outsize: usize) -> u64 {
     let mut input = input.to_vec();
     for _i in 0..iters {
         input = input.iter().rev().scan(0, |acc, x| {
-            *acc = *acc + (*x as u64);
+            *acc += *x as u64;
             Some(*acc)
         }).map(|x| (x % 10) as u8).collect();
         input.reverse();
@@ -49,7 +48,7 @@ fn solve_at_offset(input: &[u8], iters: usize, outsize: usize) -> u64 {
     }
 
     println!("{:?}", &input[0..outsize]);
-    to_number(&input[1..=outsize])
+    to_number(&input[0..outsize])
 }
 
 #[cfg(test)]
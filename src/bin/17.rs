@@ -1,8 +1,6 @@
 use std::io::{self, Read};
-use std::thread;
-use std::sync::mpsc::{sync_channel, TryRecvError};
 
-use ::aoc2019::intcode;
+use ::aoc2019::intcode::{Ascii, AsciiOutput};
 
 fn main() -> io::Result<()> {
     let mut input = String::new();
@@ -21,11 +19,11 @@ fn main() -> io::Result<()> {
             print!(",");
         }
     }
-    println!("");
+    println!();
 
     program[0] = 2;
     let out = "A,B,B,C,B,C,B,C,A,A\nL,6,R,8,L,4,R,8,L,12\nL,12,R,10,L,4\nL,12,L,6,L,4,L,4\nn\n";
-    println!("Part 02: {:?}", run_with_program(&program, &out));
+    println!("Part 02: {:?}", run_with_program(&program, out));
     Ok(())
 }
 
@@ -83,15 +81,6 @@ impl Dir {
             Dir::West => pos.x -= 1,
         };
     }
-
-    fn inverse(&self) -> Self {
-        match *self {
-            Dir::North => Dir::South,
-            Dir::South => Dir::North,
-            Dir::West => Dir::East,
-            Dir::East => Dir::West,
-        }
-    }
 }
 
 #[derive(Eq, PartialEq, Hash, Clone, Debug)]
@@ -194,40 +183,18 @@ fn commands(input: &str) -> Vec<Command> {
 }
 
 fn run_with_program(program: &[i64], code: &str) -> i64 {
-    let (inputs_sender, inputs_receiver) = sync_channel(code.len());
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
-    thread::spawn(move || state.run());
-    let mut output = String::new();
-    let mut it = code.chars();
-    let mut n = it.next();
-
-    for ch in code.chars() {
-        inputs_sender.send(ch as i64);
-    }
+    let mut ascii = Ascii::new(program);
+    code.lines().for_each(|line| ascii.send_line(line));
 
-    let mut out = 0;
-    while let Ok(o) = outputs_receiver.recv() {
-        out = o;
-    }
-
-    out
+    ascii.run().unwrap().into_iter().rev().find_map(|output| match output {
+        AsciiOutput::Value(value) => Some(value),
+        AsciiOutput::Line(_) => None,
+    }).unwrap_or(0)
 }
 
 fn run(program: &[i64]) -> String {
-    let (inputs_sender, inputs_receiver) = sync_channel(0);
-    let (outputs_sender, outputs_receiver) = sync_channel(0);
-    let mut state = intcode::State::from(0, program, inputs_receiver, outputs_sender);
-    thread::spawn(move || state.run());
-    let mut output = String::new();
-    loop {
-        match outputs_receiver.recv() {
-            Ok(character) => {
-                output.push((character as u8) as char);
-            },
-            Err(_) => break
-        }
-    }
-
-    output
+    Ascii::new(program).run().unwrap().into_iter().filter_map(|output| match output {
+        AsciiOutput::Line(line) => Some(line + "\n"),
+        AsciiOutput::Value(_) => None,
+    }).collect()
 }
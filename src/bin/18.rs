@@ -82,11 +82,11 @@ fn shortest_path(maze: &Maze) -> Option<u64> {
                         new_state.origins.remove(i);
                         new_state.origins.push(k);
 
-                        let mut positions = new_state.origins.iter().cloned().collect::<Vec<char>>();
+                        let mut positions = new_state.origins.to_vec();
                         positions.sort();
                         let positions: String = positions.into_iter().collect();
                         //println!("For next key {}, checking path that gives '{}'", k, keys_obtained);
-                        let entry = result_paths.entry((keys_obtained, positions)).or_insert(u64::max_value());
+                        let entry = result_paths.entry((keys_obtained, positions)).or_insert(u64::MAX);
                         if *entry > new_state.cost_so_far {
                             *entry = new_state.cost_so_far;
                         } else {
@@ -100,34 +100,9 @@ fn shortest_path(maze: &Maze) -> Option<u64> {
         }
     }
 
-    final_states.iter().min().map(|x| *x)
+    final_states.iter().min().copied()
 }
 
-fn accessible_keys(maze: &Maze, pos: &Addr) -> Vec<(char, Addr)> {
-    let mut to_visit = vec![*pos];
-    let mut visited = HashSet::new();
-    let mut keys = Vec::new();
-
-    while let Some(addr) = to_visit.pop() {
-        if visited.contains(&addr) {
-            continue
-        }
-
-        visited.insert(addr);
-
-        if let Some(tile) = maze.tile_at(&addr) {
-            if let Tile::Key(k) = tile {
-                keys.push((k, addr.clone()));
-            }
-        }
-
-        let neighbours = addr.neighbours();
-        let mut neighbours = neighbours.iter().filter(|n| maze.tile_at(&n).map(|t| t.walkable()).unwrap_or(false)).cloned().collect::<Vec<_>>();
-        to_visit.append(&mut neighbours);
-    }
-
-    keys
-}
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct Maze {
@@ -146,22 +121,6 @@ impl Maze {
         }}).collect::<HashMap<char, Addr>>()).collect()
     }
 
-    fn doors(&self) -> Vec<Addr> {
-        self.tiles.iter().enumerate().flat_map(|(y, line)| line.iter().enumerate().filter_map(|(x, t)| {
-            match *t {
-                Tile::Key(_) => Some(Addr{x: x as i64, y: y as i64}),
-            _ => None
-        }}).collect::<Vec<Addr>>()).collect()
-    }
-
-    fn tile(&self, tile: &Tile) -> Option<Addr> {
-        self.tiles.iter().enumerate().filter_map(|(y, line)| line.iter().enumerate().find(|(_x, t)| t == &tile).map(|(x, _)| Addr{x: x as i64, y: y as i64})).next()
-    }
-
-    fn tiles(&self, tile_spec: &Tile) -> Vec<Addr> {
-        self.tiles.iter().enumerate().flat_map(|(y, line)| line.iter().enumerate().filter(|(_x, t)| t == &tile_spec).map(|(x, _)| Addr{x: x as i64, y: y as i64}).collect::<Vec<_>>()).collect()
-    }
-
     fn tile_at(&self, addr: &Addr) -> Option<Tile> {
         if addr.y < 0 || addr.y as usize >= self.height {
             return None
@@ -175,15 +134,12 @@ impl Maze {
     }
 
     fn path_between(&self, origin: &Addr, dest: &Addr) -> Option<(Vec<Addr>, u64)> {
-        let successors = |node: &Addr| node.neighbours().iter().filter(|n| self.tile_at(&n).map(|t| t != Tile::Wall).unwrap_or(false)).cloned().map(|addr| (addr, 1)).collect::<Vec<_>>();
+        let successors = |node: &Addr| node.neighbours().iter().filter(|n| self.tile_at(n).map(|t| t != Tile::Wall).unwrap_or(false)).cloned().map(|addr| (addr, 1)).collect::<Vec<_>>();
         astar(origin, successors, |p| p.distance(dest), |p| p == dest)
     }
 
     fn entrances(&self) -> Vec<(char, Addr)> {
-        self.tiles.iter().enumerate().flat_map(|(y, line)| line.iter().enumerate().filter(|(_x, t)| match t {
-            Tile::Entrance(_) => true,
-            _ => false
-        }).map(|(x, t)| (t.symbol(), Addr{x: x as i64, y: y as i64})).collect::<Vec<_>>()).collect()
+        self.tiles.iter().enumerate().flat_map(|(y, line)| line.iter().enumerate().filter(|(_x, t)| matches!(t, Tile::Entrance(_))).map(|(x, t)| (t.symbol(), Addr{x: x as i64, y: y as i64})).collect::<Vec<_>>()).collect()
     }
 
     fn paths(&self) -> HashMap<(char, char), (HashSet<char>, u64)> {
@@ -195,15 +151,13 @@ impl Maze {
                 if let Some((tiles, cost)) = self.path_between(&entrance_addr, key_addr) {
                     // a path is invalid if we would pick up a key on the way
                     if tiles.iter().any(|t| {
-                        match self.tile_at(t) {
-                            Some(Tile::Key(k)) if k != *key => true,
-                            _ => false
-                        }}) {
+                        matches!(self.tile_at(t), Some(Tile::Key(k)) if k != *key)
+                    }) {
                         println!("Skipping path between origin and {}", key);
                         continue
                     }
                     let keys_required = tiles.into_iter().filter_map(|addr| if let Some(Tile::Door(ch)) = self.tile_at(&addr) { Some(ch.to_ascii_lowercase()) } else { None }).collect();
-                    paths.insert((entrance, key.clone()), (keys_required, cost));
+                    paths.insert((entrance, *key), (keys_required, cost));
                 }
             }
         }
@@ -212,17 +166,15 @@ impl Maze {
             for (key_dest, key_dest_addr) in keys.iter() {
                 if key_orig != key_dest {
                     if let Some((tiles, cost)) = self.path_between(key_orig_addr, key_dest_addr) {
-                        let keys_required: HashSet<char> = tiles.iter().filter_map(|addr| if let Some(Tile::Door(ch)) = self.tile_at(&addr) { Some(ch.to_ascii_lowercase()) } else { None }).collect();
+                        let keys_required: HashSet<char> = tiles.iter().filter_map(|addr| if let Some(Tile::Door(ch)) = self.tile_at(addr) { Some(ch.to_ascii_lowercase()) } else { None }).collect();
                         // a path is invalid if we would pick up a key on the way
-                        if keys_required.len() == 0 && tiles.iter().any(|t| {
-                            match self.tile_at(t) {
-                                Some(Tile::Key(k)) if k != *key_orig && k != *key_dest => true,
-                                _ => false
-                            }}) {
+                        if keys_required.is_empty() && tiles.iter().any(|t| {
+                            matches!(self.tile_at(t), Some(Tile::Key(k)) if k != *key_orig && k != *key_dest)
+                        }) {
                             println!("Skipping path between {} and {}", key_orig, key_dest);
                             continue
                         }
-                        paths.insert((key_orig.clone(), key_dest.clone()), (keys_required, cost));
+                        paths.insert((*key_orig, *key_dest), (keys_required, cost));
                     }
                 }
             }
@@ -235,15 +187,6 @@ impl Maze {
         self.tiles[addr.y as usize][addr.x as usize] = tile;
     }
 
-    fn unlock_door(&mut self, key: char) {
-        if let Some(key_addr) = self.tile(&Tile::Key(key)) {
-            self.keys_left -= 1;
-            self.tiles[key_addr.y as usize][key_addr.x as usize] = Tile::Passage;
-            if let Some(door_addr) = self.tile(&Tile::Door(key.to_ascii_uppercase())) {
-                self.tiles[door_addr.y as usize][door_addr.x as usize] = Tile::Passage;
-            }
-        }
-    }
 }
 
 impl std::str::FromStr for Maze {
@@ -258,11 +201,11 @@ impl std::str::FromStr for Maze {
                 Tile::Entrance(entrance as char)
             },
             '.' => Tile::Passage,
-            'a' ... 'z' => {
+            'a' ..= 'z' => {
                 keys_left += 1;
                 Tile::Key(ch)
             },
-            'A' ... 'Z' => Tile::Door(ch),
+            'A' ..= 'Z' => Tile::Door(ch),
             _ => unreachable!()
         }).collect::<Vec<Tile>>()).collect::<Vec<_>>();
         let width = tiles[0].len();
@@ -286,7 +229,7 @@ impl fmt::Display for Maze {
                     Tile::Key(ch) => *ch,
                 })?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -303,13 +246,6 @@ enum Tile {
 }
 
 impl Tile {
-    fn walkable(&self) -> bool {
-        match *self {
-            Tile::Wall => false,
-            Tile::Door(_) => false,
-            _ => true
-        }
-    }
 
     fn symbol(&self) -> char {
         match *self {
@@ -322,51 +258,7 @@ impl Tile {
 }
 
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
-enum Dir {
-    North,
-    South,
-    West,
-    East,
-}
 
-impl Dir {
-    fn left(&self) -> Self {
-        match *self {
-            Dir::North => Dir::West,
-            Dir::South => Dir::East,
-            Dir::West => Dir::South,
-            Dir::East => Dir::North,
-        }
-    }
-
-    fn right(&self) -> Self {
-        match *self {
-            Dir::North => Dir::East,
-            Dir::South => Dir::West,
-            Dir::West => Dir::North,
-            Dir::East => Dir::South,
-        }
-    }
-
-    fn advance(&self, pos: &mut Addr) {
-        match *self {
-            Dir::North => pos.y -= 1,
-            Dir::East => pos.x += 1,
-            Dir::South => pos.y += 1,
-            Dir::West => pos.x -= 1,
-        };
-    }
-
-    fn inverse(&self) -> Self {
-        match *self {
-            Dir::North => Dir::South,
-            Dir::South => Dir::North,
-            Dir::West => Dir::East,
-            Dir::East => Dir::West,
-        }
-    }
-}
 
 #[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 struct Addr{
@@ -396,10 +288,6 @@ impl Addr {
         vec![self.north(), self.west(), self.east(),  self.south()]
     }
 
-    fn neighbours_with_dir(&self) -> Vec<(Dir, Addr)> {
-        vec![(Dir::North, self.north()), (Dir::West, self.west()), (Dir::East, self.east()), (Dir::South, self.south())]
-    }
-
     fn distance(&self, other: &Addr) -> u64 {
         (absdiff(self.x, other.x) + absdiff(self.y, other.y)) as u64
     }
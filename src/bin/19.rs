@@ -0,0 +1,18 @@
+use std::io::{self, Read};
+
+use ::aoc2019::beam::Beam;
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut beam = Beam::new(&program);
+
+    print!("{}", beam.render(50, 50).unwrap());
+    println!("Part 01: {}", beam.count(50, 50).unwrap());
+    let (x, y) = beam.fit(100, 10_000).unwrap().unwrap();
+    println!("Part 02: {}", x * 10000 + y);
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::env;
+use std::io::{self, Read};
+
+use ::aoc2019::springscript::{self, Mode, Report, Script};
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+
+    // `--search n` looks for the shortest walking script of up to n instructions instead
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let [flag, length] = args.as_slice() {
+        if flag == "--search" {
+            match springscript::search(&program, Mode::Walk, length.parse().unwrap()).unwrap() {
+                Some((script, damage)) => println!("{}\n{}", script, damage),
+                None => println!("no script of up to {} instructions makes it", length),
+            }
+            return Ok(())
+        }
+    }
+
+    // jump if there's a hole in the next three tiles and ground to land on
+    let walk = "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK".parse::<Script>().unwrap();
+    println!("Part 01: {}", report(&program, &walk));
+
+    // same, as long as the droid can either walk on or jump again after landing
+    let run = "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nNOT E T\nNOT T T\nOR H T\nAND T J\nRUN".parse::<Script>().unwrap();
+    println!("Part 02: {}", report(&program, &run));
+
+    Ok(())
+}
+
+fn report(program: &[i64], script: &Script) -> String {
+    match springscript::run(program, script).unwrap() {
+        Report::Damage(damage) => damage.to_string(),
+        Report::Fell(frame) => format!("fell\n{}", frame),
+    }
+}
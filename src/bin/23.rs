@@ -0,0 +1,32 @@
+use std::io::{self, Read};
+
+use ::aoc2019::intcode::{Network, NetworkEvent, NAT};
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut network = Network::new(&program, 50);
+
+    let mut first_nat_packet = None;
+    let mut last_wake_up = None;
+    loop {
+        for event in network.round().unwrap() {
+            match event {
+                NetworkEvent::Sent(packet) if packet.to == NAT && first_nat_packet.is_none() => {
+                    println!("Part 01: {}", packet.y);
+                    first_nat_packet = Some(packet);
+                },
+                NetworkEvent::Idle(packet) => {
+                    if last_wake_up == Some(packet.y) {
+                        println!("Part 02: {}", packet.y);
+                        return Ok(())
+                    }
+                    last_wake_up = Some(packet.y);
+                },
+                _ => {},
+            }
+        }
+    }
+}
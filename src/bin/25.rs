@@ -0,0 +1,23 @@
+use std::io::{self, Read};
+
+use ::aoc2019::adventure::{password, Explorer};
+use ::aoc2019::intcode::Ascii;
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut explorer = Explorer::new(Ascii::new(&program)).unwrap();
+    explorer.explore().unwrap();
+
+    let mut rooms = explorer.rooms.keys().collect::<Vec<_>>();
+    rooms.sort();
+    println!("Rooms: {}", rooms.iter().map(|room| room.as_str()).collect::<Vec<_>>().join(", "));
+    println!("Items: {}", explorer.inventory.iter().map(|item| item.as_str()).collect::<Vec<_>>().join(", "));
+
+    let text = explorer.crack_checkpoint().unwrap().expect("no combination of items gets past the checkpoint");
+    println!("Part 01: {}", password(&text).unwrap_or(text));
+
+    Ok(())
+}
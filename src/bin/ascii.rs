@@ -0,0 +1,64 @@
+//! Runs an Intcode program that talks in ASCII, as a line-based REPL:
+//!
+//!     cargo run --bin ascii -- inputs/17.txt
+//!     cargo run --bin ascii -- inputs/17.txt --script commands.txt
+//!
+//! Lines typed on stdin (or read from the script) are sent whenever the program asks for input.
+//! Outputs that can't be characters are printed as `[value]`.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use ::aoc2019::intcode::{Ascii, AsciiOutput};
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (path, script) = match args.as_slice() {
+        [path] => (path, None),
+        [path, flag, script] if flag == "--script" => (path, Some(script)),
+        _ => {
+            eprintln!("usage: ascii <program> [--script <file>]");
+            std::process::exit(1);
+        },
+    };
+
+    let program = fs::read_to_string(path)?.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut ascii = Ascii::new(&program);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if let Some(script) = script {
+        let script = fs::read_to_string(script)?;
+        for output in ascii.run_script(script.lines()).unwrap() {
+            print(&mut out, &output)?;
+        }
+        return Ok(())
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        for output in ascii.run().unwrap() {
+            print(&mut out, &output)?;
+        }
+        if ascii.is_halted() {
+            break
+        }
+        out.flush()?;
+
+        match lines.next() {
+            Some(line) => ascii.send_line(&line?),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn print<W: Write>(out: &mut W, output: &AsciiOutput) -> io::Result<()> {
+    match *output {
+        AsciiOutput::Line(ref line) => writeln!(out, "{}", line),
+        AsciiOutput::Value(value) => writeln!(out, "[{}]", value),
+    }
+}
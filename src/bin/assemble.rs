@@ -0,0 +1,21 @@
+use std::io::{self, Read};
+use std::process;
+
+use ::aoc2019::intcode;
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    match intcode::assemble(&input) {
+        Ok(program) => {
+            println!("{}", program.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(","));
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Runs an Intcode program under the debugger:
+//!
+//!     cargo run --bin debugger -- inputs/13.txt
+//!     cargo run --bin debugger -- inputs/13.txt --script session.txt
+//!
+//! Commands are read from stdin (or from the script) one per line; `help` lists them.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+
+use ::aoc2019::intcode::{Command, Debugger};
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (path, script) = match args.as_slice() {
+        [path] => (path, None),
+        [path, flag, script] if flag == "--script" => (path, Some(script)),
+        _ => {
+            eprintln!("usage: debugger <program> [--script <file>]");
+            std::process::exit(1);
+        },
+    };
+
+    let program = fs::read_to_string(path)?.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut debugger = Debugger::new(&program);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if let Some(script) = script {
+        return debugger.run_script(BufReader::new(File::open(script)?), &mut out, true)
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        write!(out, "(icdb) ")?;
+        out.flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        if line.trim().is_empty() {
+            continue
+        }
+
+        match line.parse::<Command>() {
+            Ok(command) => {
+                if !debugger.execute(&command, &mut out)? {
+                    break
+                }
+            },
+            Err(e) => writeln!(out, "{}", e)?,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use std::io::{self, Read, Write};
+
+use ::aoc2019::intcode;
+
+fn main() -> io::Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    io::stdout().write_all(intcode::listing(&program).as_bytes())
+}
@@ -0,0 +1,93 @@
+//! Runs a program with a patch applied, or searches for the values of the patch's ranges that
+//! make the program produce a target:
+//!
+//!     cargo run --bin patch -- inputs/02.txt alarm.patch
+//!     cargo run --bin patch -- inputs/02.txt noun-verb.patch --target 19690720 --at 0
+//!     cargo run --bin patch -- inputs/05.txt diagnostics.patch --input 5
+//!
+//! Options:
+//!   --input <v,v,...>     values queued for input instructions
+//!   --target <v>          search for the patch making the last output, or a cell, equal to <v>
+//!   --at <addr>           compare the cell at <addr> with the target once the program halts
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+use ::aoc2019::intcode::{IntcodeError, Patch, State, Sweep};
+
+fn usage() -> ! {
+    eprintln!("usage: patch <program> <patch> [--input <v,v,...>] [--target <v> [--at <addr>]]");
+    process::exit(1);
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> T {
+    s.trim().parse().unwrap_or_else(|_| usage())
+}
+
+fn run(program: &[i64], inputs: &[i64]) -> Result<State, IntcodeError> {
+    let mut state = State::new(program);
+    inputs.iter().for_each(|&input| state.push_input(input));
+    state.run_to_halt()?;
+    Ok(state)
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let program_path = args.next().unwrap_or_else(|| usage());
+    let patch_path = args.next().unwrap_or_else(|| usage());
+
+    let program = fs::read_to_string(program_path)?.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let patch = fs::read_to_string(patch_path)?.parse::<Patch>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut inputs = Vec::new();
+    let mut target = None;
+    let mut at = None;
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--input" => inputs.extend(value.split(',').map(parse::<i64>)),
+            "--target" => target = Some(parse::<i64>(&value)),
+            "--at" => at = Some(parse::<usize>(&value)),
+            _ => usage(),
+        }
+    }
+
+    match target {
+        Some(target) => {
+            // a patch can break the program, which only rules it out
+            let found = Sweep::patch(&program, &patch).unwrap().first(|state| {
+                inputs.iter().for_each(|&input| state.push_input(input));
+                Ok(state.run_to_halt().is_ok() && match at {
+                    Some(addr) => state.memory.get(addr) == target,
+                    None => state.outputs.back() == Some(&target),
+                })
+            }).unwrap();
+            match found {
+                Some(values) => print!("{}", patch.with_values(&values)),
+                None => {
+                    eprintln!("no patch produces {}", target);
+                    process::exit(1);
+                },
+            }
+        },
+        None => {
+            let mut patched = program;
+            let applied = patch.apply(&mut patched).unwrap();
+            println!("{} of {} pokes applied", applied, patch.pokes.len());
+            match run(&patched, &inputs) {
+                Ok(state) => {
+                    println!("outputs: {}", state.outputs.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(","));
+                    println!("address 0: {}", state.memory.get(0));
+                },
+                Err(e) => eprintln!("stopped: {}", e),
+            }
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Runs a program with tracing enabled and prints where it spent its time:
+//!
+//!     cargo run --release --bin trace -- inputs/13.txt --poke 0=2 --default-input 0 --trace day13.trace
+//!
+//! Options:
+//!   --input <v,v,...>     values queued for input instructions
+//!   --default-input <v>   value fed whenever the queued inputs run out, instead of stopping
+//!   --poke <addr>=<v>     overwrite a cell before running, can be repeated
+//!   --patch <file>        apply a patch file before running
+//!   --max-steps <n>       stop after executing <n> instructions
+//!   --timeout <ms>        stop after running for <ms> milliseconds
+//!   --detect-loops        stop when stuck in a loop that can't end
+//!   --self-mod            report the writes the program makes to its own code
+//!   --trace <file>        write every executed instruction to <file>
+//!   --top <n>             number of hot spots to list (default 20)
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::process;
+use std::time::Duration;
+
+use ::aoc2019::intcode::{Limits, Memory, Patch, SelfMod, State, Status, Tracer};
+
+fn usage() -> ! {
+    eprintln!("usage: trace <program> [--input <v,v,...>] [--default-input <v>] [--poke <addr>=<v>]... [--patch <file>] [--max-steps <n>] [--timeout <ms>] [--detect-loops] [--self-mod] [--trace <file>] [--top <n>]");
+    process::exit(1);
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> T {
+    s.trim().parse().unwrap_or_else(|_| usage())
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| usage());
+
+    let mut program = fs::read_to_string(path)?.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+    let mut inputs = Vec::new();
+    let mut default_input = None;
+    let mut trace_path = None;
+    let mut top = 20;
+    let mut limits = Limits::default();
+    let mut selfmod = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--detect-loops" => {
+                limits.loops = true;
+                continue
+            },
+            "--self-mod" => {
+                selfmod = true;
+                continue
+            },
+            _ => {},
+        }
+
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--input" => inputs.extend(value.split(',').map(parse::<i64>)),
+            "--default-input" => default_input = Some(parse(&value)),
+            "--poke" => {
+                let mut parts = value.splitn(2, '=');
+                let addr: usize = parse(parts.next().unwrap_or_else(|| usage()));
+                let v = parse(parts.next().unwrap_or_else(|| usage()));
+                Memory::check(addr).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                });
+                if addr >= program.len() {
+                    program.resize(addr + 1, 0);
+                }
+                program[addr] = v;
+            },
+            "--patch" => {
+                let patch = fs::read_to_string(value)?.parse::<Patch>().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                });
+                // parsing rejects addresses past the end of memory
+                patch.apply(&mut program).unwrap();
+            },
+            "--max-steps" => limits.steps = Some(parse(&value)),
+            "--timeout" => limits.time = Some(Duration::from_millis(parse(&value))),
+            "--trace" => trace_path = Some(value),
+            "--top" => top = parse(&value),
+            _ => usage(),
+        }
+    }
+
+    let mut state = State::new(&program);
+    state.limits = limits;
+    if selfmod {
+        state.selfmod = Some(SelfMod::new());
+    }
+    state.tracer = Some(match trace_path {
+        Some(path) => Tracer::to_writer(BufWriter::new(File::create(path)?)),
+        None => Tracer::new(),
+    });
+    inputs.into_iter().for_each(|input| state.push_input(input));
+
+    let mut outputs = 0;
+    loop {
+        match state.run() {
+            Ok(Status::Output(_)) => outputs += 1,
+            Ok(Status::NeedsInput) => match default_input {
+                Some(input) => state.push_input(input),
+                None => {
+                    eprintln!("stopped: waiting for input at {}", state.pc);
+                    break
+                },
+            },
+            Ok(Status::Halted) => break,
+            Err(e) => {
+                eprintln!("stopped: {}", e);
+                break
+            },
+        }
+    }
+
+    let mut tracer = state.tracer.take().unwrap();
+    tracer.flush()?;
+    if let Some(e) = tracer.error.take() {
+        return Err(e)
+    }
+
+    println!("{} outputs", outputs);
+    print!("{}", tracer.summary(&state.memory, top));
+    if let Some(selfmod) = state.selfmod {
+        print!("\n{}", selfmod);
+    }
+
+    Ok(())
+}
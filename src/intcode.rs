@@ -1,38 +1,186 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
 
-use std::io::{self, Read};
-use std::sync::mpsc::{sync_channel, SyncSender, Receiver, RecvError, TryRecvError};
-use std::thread;
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{SyncSender, Receiver};
+use std::time::{Duration, Instant};
+
+mod ascii;
+mod asm;
+mod debugger;
+mod disasm;
+pub(crate) mod fields;
+mod io;
+mod limits;
+mod memory;
+mod network;
+mod patch;
+mod scheduler;
+mod selfmod;
+mod snapshot;
+mod sweep;
+mod topology;
+mod trace;
+
+pub use self::ascii::{Ascii, AsciiOutput};
+pub use self::asm::{assemble, AsmError};
+pub use self::debugger::{Command, Debugger};
+pub use self::disasm::{decode, disassemble, listing, Instruction, Opcode, Param};
+pub use self::io::{Closed, Input, Output, Text};
+pub use self::limits::Limits;
+pub use self::memory::{Memory, MemoryError, MAX_LEN};
+pub use self::network::{Network, NetworkError, NetworkEvent, Packet, IDLE_ROUNDS, NAT};
+pub use self::patch::{Condition, Patch, PatchError, Poke, Values};
+pub use self::scheduler::{Edge, Outcome, Scheduler, SchedulerError};
+pub use self::selfmod::{CodeWrite, SelfMod, Target};
+pub use self::snapshot::Snapshot;
+pub use self::sweep::{Space, Sweep, SweepError};
+pub use self::topology::Topology;
+pub use self::trace::{TraceEntry, Tracer};
 
-#[derive(Debug)]
 pub struct State {
     pub id: usize,
-    pub inputs: Receiver<i64>,
-    pub outputs: SyncSender<i64>,
-    pub program: HashMap<i64, i64>,
+    pub inputs: VecDeque<i64>,
+    /// Outputs collected by `run_to_input` that the caller hasn't taken yet.
+    pub outputs: VecDeque<i64>,
+    /// Read from once `inputs` runs out.
+    pub source: Option<Box<dyn Input + Send>>,
+    /// Receives every output, which `run` then doesn't hand back.
+    pub sink: Option<Box<dyn Output + Send>>,
+    pub memory: Memory,
     pub pc: i64,
     pub relative_base: i64,
+    pub tracer: Option<Tracer>,
+    pub selfmod: Option<SelfMod>,
+    /// Number of instructions executed so far.
+    pub steps: usize,
+    pub limits: Limits,
+    watch: Option<limits::Watch>,
 }
 
 impl State {
+    /// Builds a machine that blocks on `inputs` and sends every output to `outputs`, meant to be
+    /// run on its own thread.
     pub fn from(id: usize, program: &[i64], inputs: Receiver<i64>, outputs: SyncSender<i64>) -> Self {
-        let mut state = State{id, inputs , outputs, pc: 0, program: HashMap::with_capacity(program.len()), relative_base: 0};
+        let mut state = State::new(program).with_input(inputs).with_output(outputs);
+        state.id = id;
+        state
+    }
+
+    /// Builds a synchronous machine: `run` hands control back to the caller whenever the program
+    /// needs an input that hasn't been pushed yet or produces an output.
+    pub fn new(program: &[i64]) -> Self {
+        State{id: 0, inputs: VecDeque::new(), outputs: VecDeque::new(), source: None, sink: None, pc: 0, memory: Memory::from(program), relative_base: 0, tracer: None, selfmod: None, steps: 0, limits: Limits::default(), watch: None}
+    }
+
+    pub fn with_input<I: Input + Send + 'static>(mut self, input: I) -> Self {
+        self.source = Some(Box::new(input));
+        self
+    }
+
+    pub fn with_output<O: Output + Send + 'static>(mut self, output: O) -> Self {
+        self.sink = Some(Box::new(output));
+        self
+    }
+
+    /// Queues a value for the next input instruction. Queued values are consumed before the
+    /// source, if any.
+    pub fn push_input(&mut self, value: i64) {
+        self.inputs.push_back(value);
+    }
+
+    /// Runs until the machine halts or, in synchronous mode, until it suspends on I/O. Calling
+    /// `run` again resumes where it left off.
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        let started = self.limits.time.map(|_| Instant::now());
+        self.watch = if self.limits.loops { Some(limits::Watch::new(&self.memory)) } else { None };
+        loop {
+            self.check_limits(started)?;
+            match exec_op(self)? {
+                Next::Continue => {},
+                Next::NeedsInput => return Ok(Status::NeedsInput),
+                Next::Output(value) => return Ok(Status::Output(value)),
+                Next::Exit(_) => return Ok(Status::Halted),
+            }
+        }
+    }
 
-        for (i, op) in program.iter().enumerate() {
-            state.program.insert(i as i64, *op);
+    fn check_limits(&mut self, started: Option<Instant>) -> Result<(), IntcodeError> {
+        let (pc, instruction) = (self.pc, self.memory.get(self.pc.max(0) as usize));
+        if let Some(steps) = self.limits.steps {
+            if self.steps >= steps {
+                return Err(IntcodeError::StepLimit { pc, instruction, steps: self.steps })
+            }
         }
+        if let (Some(limit), Some(started)) = (self.limits.time, started) {
+            if self.steps.is_multiple_of(limits::CLOCK_INTERVAL) && started.elapsed() >= limit {
+                return Err(IntcodeError::TimeLimit { pc, instruction, limit })
+            }
+        }
+        if let Some(ref mut watch) = self.watch {
+            if instruction % 100 == 3 || instruction % 100 == 4 {
+                watch.io();
+            } else if let Some((start, end)) = watch.step(pc, self.relative_base) {
+                return Err(IntcodeError::InfiniteLoop { pc, instruction, start, end })
+            }
+        }
+        Ok(())
+    }
 
-        state
+    /// Like `run`, but buffers outputs in `outputs` instead of handing them back one at a time:
+    /// only returns once the machine needs an input or halts.
+    pub fn run_to_input(&mut self) -> Result<Status, IntcodeError> {
+        loop {
+            match self.run()? {
+                Status::Output(value) => self.outputs.push_back(value),
+                status => return Ok(status),
+            }
+        }
+    }
+
+    /// Runs the program to completion, buffering every output in `outputs`. Running out of
+    /// inputs is reported as `InputClosed`, since there's no one to hand control back to.
+    pub fn run_to_halt(&mut self) -> Result<(), IntcodeError> {
+        match self.run_to_input()? {
+            Status::NeedsInput => Err(IntcodeError::InputClosed { pc: self.pc, instruction: self.memory.get(self.pc as usize) }),
+            _ => Ok(()),
+        }
     }
+}
 
-    pub fn run(&mut self) {
-        while let Next::Continue = exec_op(self) {}
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("State")
+            .field("id", &self.id)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("source", &self.source.as_ref().map(|_| ".."))
+            .field("sink", &self.sink.as_ref().map(|_| ".."))
+            .field("memory", &self.memory)
+            .field("pc", &self.pc)
+            .field("relative_base", &self.relative_base)
+            .field("tracer", &self.tracer)
+            .field("selfmod", &self.selfmod.as_ref().map(|selfmod| selfmod.writes.len()))
+            .field("steps", &self.steps)
+            .field("limits", &self.limits)
+            .finish()
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Status {
+    /// The program is waiting on an input instruction; push a value and `run` again.
+    NeedsInput,
+    /// The program produced a value; `run` again to continue.
+    Output(i64),
+    Halted,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Next {
     Continue,
+    NeedsInput,
+    Output(i64),
     Exit(Option<i64>),
 }
 
@@ -40,160 +188,279 @@ const POSITION: i64 = 0;
 const IMMEDIATE: i64 = 1;
 const RELATIVE: i64 = 2;
 
-fn addr_from_param(state: &State, op:i64, param_position: i64) -> i64 {
-    let addr = state.pc + param_position;
-    let param = *state.program.get(&addr).expect(&format!("Expected value at position: {}, got none", addr));
-    let diviser = match param_position {
-        1 => 100,
-        2 => 1000,
-        3 => 10000,
-        _ => unreachable!()
-    };
-    let mode = (op / diviser) % 10;
-
-    match mode {
+/// Raised when the machine runs into an instruction it can't execute. Every variant carries the
+/// pc and the raw instruction that were being executed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IntcodeError {
+    UnknownOpcode { pc: i64, instruction: i64 },
+    InvalidMode { pc: i64, instruction: i64, param: i64, mode: i64 },
+    ImmediateWrite { pc: i64, instruction: i64, param: i64 },
+    NegativeAddress { pc: i64, instruction: i64, addr: i64 },
+    AddressTooLarge { pc: i64, instruction: i64, addr: i64 },
+    InputClosed { pc: i64, instruction: i64 },
+    /// The machine halted where the caller was waiting on an output.
+    HaltedWithoutOutput { pc: i64, instruction: i64 },
+    StepLimit { pc: i64, instruction: i64, steps: usize },
+    TimeLimit { pc: i64, instruction: i64, limit: Duration },
+    /// The machine came back to a state it was in, without any I/O since: it will go around the
+    /// loop between the `start` and `end` pcs forever.
+    InfiniteLoop { pc: i64, instruction: i64, start: i64, end: i64 },
+    /// An addition or multiplication, of values or in working out an address, doesn't fit in an
+    /// `i64`.
+    Overflow { pc: i64, instruction: i64 },
+}
+
+impl IntcodeError {
+    pub fn pc(&self) -> i64 {
+        match *self {
+            IntcodeError::UnknownOpcode { pc, .. } |
+            IntcodeError::InvalidMode { pc, .. } |
+            IntcodeError::ImmediateWrite { pc, .. } |
+            IntcodeError::NegativeAddress { pc, .. } |
+            IntcodeError::AddressTooLarge { pc, .. } |
+            IntcodeError::InputClosed { pc, .. } |
+            IntcodeError::HaltedWithoutOutput { pc, .. } |
+            IntcodeError::StepLimit { pc, .. } |
+            IntcodeError::TimeLimit { pc, .. } |
+            IntcodeError::InfiniteLoop { pc, .. } |
+            IntcodeError::Overflow { pc, .. } => pc,
+        }
+    }
+
+    pub fn instruction(&self) -> i64 {
+        match *self {
+            IntcodeError::UnknownOpcode { instruction, .. } |
+            IntcodeError::InvalidMode { instruction, .. } |
+            IntcodeError::ImmediateWrite { instruction, .. } |
+            IntcodeError::NegativeAddress { instruction, .. } |
+            IntcodeError::AddressTooLarge { instruction, .. } |
+            IntcodeError::InputClosed { instruction, .. } |
+            IntcodeError::HaltedWithoutOutput { instruction, .. } |
+            IntcodeError::StepLimit { instruction, .. } |
+            IntcodeError::TimeLimit { instruction, .. } |
+            IntcodeError::InfiniteLoop { instruction, .. } |
+            IntcodeError::Overflow { instruction, .. } => instruction,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntcodeError::UnknownOpcode { pc, instruction } =>
+                write!(f, "unknown opcode {} at {}", instruction % 100, pc),
+            IntcodeError::InvalidMode { pc, instruction, param, mode } =>
+                write!(f, "invalid mode {} for parameter {} of {} at {}", mode, param, instruction, pc),
+            IntcodeError::ImmediateWrite { pc, instruction, param } =>
+                write!(f, "parameter {} of {} at {} is written to but in immediate mode", param, instruction, pc),
+            IntcodeError::NegativeAddress { pc, instruction, addr } =>
+                write!(f, "access to negative address {} by {} at {}", addr, instruction, pc),
+            IntcodeError::AddressTooLarge { pc, instruction, addr } =>
+                write!(f, "write to address {} past the end of memory by {} at {}", addr, instruction, pc),
+            IntcodeError::InputClosed { pc, instruction } =>
+                write!(f, "input closed while {} at {} was waiting on it", instruction, pc),
+            IntcodeError::HaltedWithoutOutput { pc, .. } =>
+                write!(f, "halted at {} without an output", pc),
+            IntcodeError::StepLimit { pc, steps, .. } =>
+                write!(f, "step limit reached at {} after {} steps", pc, steps),
+            IntcodeError::TimeLimit { pc, limit, .. } =>
+                write!(f, "time limit of {:?} reached at {}", limit, pc),
+            IntcodeError::InfiniteLoop { pc, start, end, .. } =>
+                write!(f, "infinite loop between {} and {}, stuck at {}", start, end, pc),
+            IntcodeError::Overflow { pc, instruction } =>
+                write!(f, "arithmetic overflow in {} at {}", instruction, pc),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+fn read(state: &State, op: i64, addr: i64) -> Result<i64, IntcodeError> {
+    if addr < 0 {
+        return Err(IntcodeError::NegativeAddress { pc: state.pc, instruction: op, addr })
+    }
+
+    Ok(state.memory.get(addr as usize))
+}
+
+fn write(state: &mut State, op: i64, addr: i64, value: i64) -> Result<(), IntcodeError> {
+    if addr < 0 {
+        return Err(IntcodeError::NegativeAddress { pc: state.pc, instruction: op, addr })
+    }
+
+    let old = state.memory.get(addr as usize);
+    if state.memory.set(addr as usize, value).is_err() {
+        return Err(IntcodeError::AddressTooLarge { pc: state.pc, instruction: op, addr })
+    }
+    if let Some(ref mut watch) = state.watch {
+        watch.wrote(addr as usize, old, value);
+    }
+    if let Some(ref mut selfmod) = state.selfmod {
+        selfmod.wrote(&state.memory, state.pc as usize, op, addr as usize, old, value);
+    }
+    Ok(())
+}
+
+fn checked(state: &State, op: i64, value: Option<i64>) -> Result<i64, IntcodeError> {
+    value.ok_or(IntcodeError::Overflow { pc: state.pc, instruction: op })
+}
+
+fn mode(op: i64, param_position: i64) -> i64 {
+    (op / 10i64.pow(param_position as u32 + 1)) % 10
+}
+
+fn addr_from_param(state: &State, op: i64, param_position: i64) -> Result<i64, IntcodeError> {
+    let param = read(state, op, checked(state, op, state.pc.checked_add(param_position))?)?;
+
+    match mode(op, param_position) {
         POSITION => {
-            param
+            Ok(param)
         },
         RELATIVE => {
-            param + state.relative_base
+            checked(state, op, param.checked_add(state.relative_base))
         },
-        _ => unreachable!()
+        IMMEDIATE => Err(IntcodeError::ImmediateWrite { pc: state.pc, instruction: op, param: param_position }),
+        mode => Err(IntcodeError::InvalidMode { pc: state.pc, instruction: op, param: param_position, mode }),
     }
 }
 
-fn value_from_param(state: &State, op:i64, param_position: i64) -> i64 {
-    let addr = state.pc + param_position;
-    let param = *state.program.get(&addr).expect(&format!("Expected value at position: {}, got none", addr));
-    let diviser = match param_position {
-        1 => 100,
-        2 => 1000,
-        3 => 10000,
-        _ => unreachable!()
-    };
-    let mode = (op / diviser) % 10;
+fn value_from_param(state: &State, op: i64, param_position: i64) -> Result<i64, IntcodeError> {
+    let param = read(state, op, checked(state, op, state.pc.checked_add(param_position))?)?;
 
-    match mode {
-        IMMEDIATE => param,
+    match mode(op, param_position) {
+        IMMEDIATE => Ok(param),
         POSITION => {
-            //*state.program.get(&param).expect(&format!("Expected to get value from {} at {}", param, state.pc))
-            *state.program.get(&param).unwrap_or(&0)
+            read(state, op, param)
         },
         RELATIVE => {
-            //*state.program.get(&(param + state.relative_base)).expect(&format!("Expected to get value from {} ({} + {}), got none", param + state.relative_base, param, state.relative_base))
-            *state.program.get(&(param + state.relative_base)).unwrap_or(&0)
+            read(state, op, checked(state, op, param.checked_add(state.relative_base))?)
         },
-        _ => unreachable!()
+        mode => Err(IntcodeError::InvalidMode { pc: state.pc, instruction: op, param: param_position, mode }),
+    }
+}
+
+pub fn exec_op(state: &mut State) -> Result<Next, IntcodeError> {
+    if let Some(ref mut selfmod) = state.selfmod {
+        if state.pc >= 0 {
+            selfmod.executing(state.pc as usize, state.memory.get(state.pc as usize));
+        }
     }
+    let next = match state.tracer {
+        None => execute(state),
+        Some(_) => trace::traced(state),
+    }?;
+    if next != Next::NeedsInput {
+        state.steps += 1;
+    }
+    Ok(next)
 }
 
-pub fn exec_op(state: &mut State) -> Next {
-    let op = state.program.get(&state.pc).unwrap();
+fn execute(state: &mut State) -> Result<Next, IntcodeError> {
+    let op = read(state, 0, state.pc)?;
     match op % 100 {
         99 => {
-            return Next::Exit(None)
+            Ok(Next::Exit(None))
         },
         1 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
+            let first_value = value_from_param(state, op, 1)?;
+            let second_value = value_from_param(state, op, 2)?;
+            let addr = addr_from_param(state, op, 3)?;
 
-            let sum = first_value + second_value;
-            state.program.insert(addr, sum);
+            let sum = checked(state, op, first_value.checked_add(second_value))?;
+            write(state, op, addr, sum)?;
 
             state.pc += 4;
 
-            Next::Continue
+            Ok(Next::Continue)
         },
         2 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
+            let first_value = value_from_param(state, op, 1)?;
+            let second_value = value_from_param(state, op, 2)?;
+            let addr = addr_from_param(state, op, 3)?;
 
-            let product = first_value * second_value;
-            state.program.insert(addr, product);
+            let product = checked(state, op, first_value.checked_mul(second_value))?;
+            write(state, op, addr, product)?;
             state.pc += 4;
 
-            Next::Continue
+            Ok(Next::Continue)
         },
         3 => {
-            let addr = addr_from_param(&state, *op, 1);
-            //println!("[{}] Got a {} from the input channel", state.id, value);
-            match state.inputs.recv() {
-                Ok(value) => {
-                    state.program.insert(addr, value);
-                    state.pc += 2;
-                    Next::Continue
+            let addr = addr_from_param(state, op, 1)?;
+            let value = match state.inputs.pop_front() {
+                Some(value) => value,
+                None => match state.source.as_mut().map(|source| source.read()) {
+                    Some(Ok(Some(value))) => value,
+                    Some(Err(Closed)) => return Err(IntcodeError::InputClosed { pc: state.pc, instruction: op }),
+                    // leave the pc on the instruction so it is retried once an input is available
+                    Some(Ok(None)) | None => return Ok(Next::NeedsInput),
                 },
-                _ => { Next::Exit(None) }
-
-            }
+            };
+            write(state, op, addr, value)?;
+            state.pc += 2;
+            Ok(Next::Continue)
         },
         4 => {
-            let value = value_from_param(&state, *op, 1);
+            let value = value_from_param(state, op, 1)?;
 
-            if let Err(_) = state.outputs.send(value) {
-                // receiver has dropped, it's time to bail out
-                return Next::Exit(Some(value))
+            if let Some(ref mut sink) = state.sink {
+                if sink.write(value).is_err() {
+                    // receiver has dropped, it's time to bail out
+                    return Ok(Next::Exit(Some(value)))
+                }
+                state.pc += 2;
+                Ok(Next::Continue)
+            } else {
+                state.pc += 2;
+                Ok(Next::Output(value))
             }
-            state.pc += 2;
-            Next::Continue
         },
         5 => {
-            let value = value_from_param(&state, *op, 1);
+            let value = value_from_param(state, op, 1)?;
 
             if value != 0 {
-                let value = value_from_param(&state, *op, 2);
+                let value = value_from_param(state, op, 2)?;
                 state.pc = value;
             } else {
                 state.pc += 3;
             }
-            Next::Continue
+            Ok(Next::Continue)
         },
         6 => {
-            let value = value_from_param(&state, *op, 1);
+            let value = value_from_param(state, op, 1)?;
 
             if value == 0 {
-                let value = value_from_param(&state, *op, 2);
+                let value = value_from_param(state, op, 2)?;
                 state.pc = value;
             } else {
                 state.pc += 3;
             }
-            Next::Continue
+            Ok(Next::Continue)
         },
         7 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
+            let first_value = value_from_param(state, op, 1)?;
+            let second_value = value_from_param(state, op, 2)?;
+            let addr = addr_from_param(state, op, 3)?;
 
-            if first_value < second_value {
-                state.program.insert(addr, 1);
-            } else {
-                state.program.insert(addr, 0);
-            }
+            write(state, op, addr, if first_value < second_value { 1 } else { 0 })?;
             state.pc += 4;
-            Next::Continue
+            Ok(Next::Continue)
         },
         8 => {
-            let first_value = value_from_param(&state, *op, 1);
-            let second_value = value_from_param(&state, *op, 2);
-            let addr = addr_from_param(&state, *op, 3);
+            let first_value = value_from_param(state, op, 1)?;
+            let second_value = value_from_param(state, op, 2)?;
+            let addr = addr_from_param(state, op, 3)?;
 
-            if first_value == second_value {
-                state.program.insert(addr, 1);
-            } else {
-                state.program.insert(addr, 0);
-            }
+            write(state, op, addr, if first_value == second_value { 1 } else { 0 })?;
             state.pc += 4;
-            Next::Continue
+            Ok(Next::Continue)
         },
         9 => {
-            let value = value_from_param(&state, *op, 1);
-            state.relative_base += value;
+            let value = value_from_param(state, op, 1)?;
+            state.relative_base = checked(state, op, state.relative_base.checked_add(value))?;
             state.pc += 2;
-            Next::Continue
+            Ok(Next::Continue)
         }
-        _ => unreachable!(),
+        _ => Err(IntcodeError::UnknownOpcode { pc: state.pc, instruction: op }),
     }
 }
 
@@ -201,15 +468,124 @@ pub fn exec_op(state: &mut State) -> Next {
 mod tests {
     use super::*;
 
+    use std::sync::mpsc::sync_channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
 
     #[test]
-    fn example_part1() {
-        let program: &[i64] = &[104,1125899906842624,99][..];
-        assert_eq!(run(program, 1), 1125899906842624);
+    fn test_address_too_large() {
+        let mut state = State::new(&[1101,1,1,1_000_000_000_000_000,99]);
+        assert_eq!(state.run(), Err(IntcodeError::AddressTooLarge { pc: 0, instruction: 1101, addr: 1_000_000_000_000_000 }));
     }
 
     #[test]
-    fn example_part2() {
+    fn test_overflow() {
+        let mut state = State::new(&[1101,i64::MAX,1,0,99]);
+        assert_eq!(state.run(), Err(IntcodeError::Overflow { pc: 0, instruction: 1101 }));
+        let mut state = State::new(&[1102,i64::MAX,2,0,99]);
+        assert_eq!(state.run(), Err(IntcodeError::Overflow { pc: 0, instruction: 1102 }));
+        let mut state = State::new(&[109,i64::MAX,109,1,99]);
+        assert_eq!(state.run(), Err(IntcodeError::Overflow { pc: 2, instruction: 109 }));
+        // the relative address itself overflows
+        let mut state = State::new(&[109,i64::MAX,204,1,99]);
+        assert_eq!(state.run(), Err(IntcodeError::Overflow { pc: 2, instruction: 204 }));
     }
-}
 
+    #[test]
+    fn test_large_output() {
+        let mut state = State::new(&[104,1125899906842624,99]);
+        assert_eq!(state.run(), Ok(Status::Output(1125899906842624)));
+        assert_eq!(state.run(), Ok(Status::Halted));
+        assert_eq!(state.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_suspends_on_input() {
+        // doubles every input until it receives a 0
+        let program = [3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0];
+        let mut state = State::new(&program[..]);
+
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+        assert_eq!(state.pc, 0);
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+
+        state.push_input(21);
+        assert_eq!(state.run(), Ok(Status::Output(42)));
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+
+        state.push_input(5);
+        state.push_input(0);
+        assert_eq!(state.run(), Ok(Status::Output(10)));
+        assert_eq!(state.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_run_to_halt() {
+        let program = [3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0];
+        let mut state = State::new(&program[..]);
+        state.push_input(3);
+        state.push_input(4);
+        assert_eq!(state.run_to_halt(), Err(IntcodeError::InputClosed { pc: 0, instruction: 3 }));
+        assert_eq!(state.outputs, vec![6, 8]);
+        assert_eq!(state.steps, 10);
+
+        state.push_input(0);
+        assert_eq!(state.run_to_halt(), Ok(()));
+        assert_eq!(state.outputs, vec![6, 8]);
+        assert_eq!(state.steps, 13);
+    }
+
+    #[test]
+    fn test_channels() {
+        let (inputs_sender, inputs_receiver) = sync_channel(1);
+        let (outputs_sender, outputs_receiver) = sync_channel(0);
+        let mut state = State::from(0, &[3,9,1002,9,3,9,4,9,99,0][..], inputs_receiver, outputs_sender);
+        let handle = thread::spawn(move || state.run());
+
+        inputs_sender.send(14).unwrap();
+        assert_eq!(outputs_receiver.recv(), Ok(42));
+        assert_eq!(handle.join().unwrap(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(State::new(&[42]).run(), Err(IntcodeError::UnknownOpcode { pc: 0, instruction: 42 }));
+        assert_eq!(State::new(&[1101,1,1,0,1105,1,-1]).run(), Err(IntcodeError::NegativeAddress { pc: -1, instruction: 0, addr: -1 }));
+        assert_eq!(State::new(&[1,0,0,0,1,-3,0,0]).run(), Err(IntcodeError::NegativeAddress { pc: 4, instruction: 1, addr: -3 }));
+        assert_eq!(State::new(&[11101,1,1,0]).run(), Err(IntcodeError::ImmediateWrite { pc: 0, instruction: 11101, param: 3 }));
+        assert_eq!(State::new(&[301,1,1,0]).run(), Err(IntcodeError::InvalidMode { pc: 0, instruction: 301, param: 1, mode: 3 }));
+
+        let (inputs_sender, inputs_receiver) = sync_channel(0);
+        let (outputs_sender, _outputs_receiver) = sync_channel(0);
+        let mut state = State::from(0, &[1101,1,1,0,3,0,99][..], inputs_receiver, outputs_sender);
+        drop(inputs_sender);
+        let error = state.run().unwrap_err();
+        assert_eq!(error, IntcodeError::InputClosed { pc: 4, instruction: 3 });
+        assert_eq!((error.pc(), error.instruction()), (4, 3));
+        assert_eq!(error.to_string(), "input closed while 3 at 4 was waiting on it");
+    }
+
+    #[test]
+    fn test_io() {
+        // doubles every input until it receives a 0
+        let program = [3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0];
+        let outputs = Arc::new(Mutex::new(Vec::new()));
+        let sink = outputs.clone();
+        let mut state = State::new(&program[..])
+            .with_input(VecDeque::from(vec![1, 2]))
+            .with_output(move |value| sink.lock().unwrap().push(value));
+
+        state.push_input(3);
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+        assert_eq!(*outputs.lock().unwrap(), vec![6, 2, 4]);
+
+        let mut values = vec![5, 0].into_iter();
+        state.source = Some(Box::new(move || values.next()));
+        assert_eq!(state.run(), Ok(Status::Halted));
+        assert_eq!(*outputs.lock().unwrap(), vec![6, 2, 4, 10]);
+
+        let mut state = State::new(&program[..]).with_input(Text(&b"7\n0\n"[..]));
+        assert_eq!(state.run(), Ok(Status::Output(14)));
+        assert_eq!(state.run(), Ok(Status::Halted));
+    }
+}
@@ -0,0 +1,143 @@
+use std::mem;
+
+use super::{IntcodeError, State, Status};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsciiOutput {
+    /// A line of text, without its newline.
+    Line(String),
+    /// An output outside of the ASCII range, usually the answer the program computed.
+    Value(i64),
+}
+
+/// Drives a program that talks in ASCII text: inputs are sent as lines and outputs are read back
+/// as lines, apart from the values that can't be characters.
+#[derive(Debug)]
+pub struct Ascii {
+    pub state: State,
+    halted: bool,
+}
+
+impl Ascii {
+    pub fn new(program: &[i64]) -> Self {
+        Ascii::from(State::new(program))
+    }
+
+    /// Queues a line of input, newline included.
+    pub fn send_line(&mut self, line: &str) {
+        line.bytes().for_each(|byte| self.state.push_input(byte as i64));
+        self.state.push_input('\n' as i64);
+    }
+
+    /// Runs until the program waits for input or halts, returning what it output meanwhile. Text
+    /// that isn't followed by a newline yet, like a prompt or text right before a value, is
+    /// returned as a line of its own.
+    pub fn run(&mut self) -> Result<Vec<AsciiOutput>, IntcodeError> {
+        let mut outputs = Vec::new();
+        let mut line = String::new();
+        loop {
+            match self.state.run()? {
+                Status::Output(10) => outputs.push(AsciiOutput::Line(mem::take(&mut line))),
+                Status::Output(value) if (0..128).contains(&value) => line.push(value as u8 as char),
+                Status::Output(value) => {
+                    if !line.is_empty() {
+                        outputs.push(AsciiOutput::Line(mem::take(&mut line)));
+                    }
+                    outputs.push(AsciiOutput::Value(value));
+                },
+                status => {
+                    if !line.is_empty() {
+                        outputs.push(AsciiOutput::Line(line));
+                    }
+                    self.halted = status == Status::Halted;
+                    return Ok(outputs)
+                },
+            }
+        }
+    }
+
+    /// Sends each line of `script` whenever the program asks for input, until the script or the
+    /// program ends, and returns everything it output.
+    pub fn run_script<'a, I: IntoIterator<Item = &'a str>>(&mut self, script: I) -> Result<Vec<AsciiOutput>, IntcodeError> {
+        let mut outputs = self.run()?;
+        for line in script {
+            if self.halted {
+                break
+            }
+            self.send_line(line);
+            outputs.extend(self.run()?);
+        }
+        Ok(outputs)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// An independent copy of the program, to try things out on.
+    pub fn fork(&self) -> Self {
+        Ascii{state: self.state.fork(), halted: self.halted}
+    }
+}
+
+impl From<State> for Ascii {
+    fn from(state: State) -> Self {
+        Ascii{state, halted: false}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // asks for a line, then prints it back followed by its length
+    const ECHO: &str = r#"
+                out #62
+        read:   in char
+                eq char, #10, done
+                jnz done, #end
+                out char
+                add length, #1, length
+                jz #0, #read
+        end:    out #10
+                mul length, #1000, length
+                out length
+                halt
+        char:   db 0
+        done:   db 0
+        length: db 0
+    "#;
+
+    #[test]
+    fn test_run() {
+        let mut ascii = Ascii::new(&assemble(ECHO).unwrap());
+        assert_eq!(ascii.run(), Ok(vec![AsciiOutput::Line(">".to_string())]));
+        assert!(!ascii.is_halted());
+
+        ascii.send_line("hello");
+        assert_eq!(ascii.run(), Ok(vec![AsciiOutput::Line("hello".to_string()), AsciiOutput::Value(5000)]));
+        assert!(ascii.is_halted());
+    }
+
+    #[test]
+    fn test_value_after_text() {
+        // "=", 1000, "!"
+        let mut ascii = Ascii::new(&assemble("out #61\nout #1000\nout #33\nhalt").unwrap());
+        assert_eq!(ascii.run(), Ok(vec![
+            AsciiOutput::Line("=".to_string()),
+            AsciiOutput::Value(1000),
+            AsciiOutput::Line("!".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_run_script() {
+        let mut ascii = Ascii::new(&assemble(ECHO).unwrap());
+        assert_eq!(ascii.run_script(vec!["a", "ignored"]), Ok(vec![
+            AsciiOutput::Line(">".to_string()),
+            AsciiOutput::Line("a".to_string()),
+            AsciiOutput::Value(1000),
+        ]));
+    }
+}
@@ -0,0 +1,335 @@
+//! A small assembler for Intcode, accepting the syntax produced by `listing`:
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! x = 1               ; constants, handy to name relative-base offsets
+//!         arb #frame
+//! loop:   in @x       ; `#` for immediate and `@` for relative operands
+//!         jz @x, #done
+//!         mul @x, #2, @x
+//!         out @x
+//!         jnz #1, #loop
+//! done:   halt
+//! frame:  db 0, 0, "hi"
+//! ```
+//!
+//! Plain operands are read in position mode. Operands can be a number, a label or a constant,
+//! optionally offset with `+` and `-`. A numeric label like `0012:` asserts the current address,
+//! which is what lets a disassembly listing be assembled back. `DATA` is accepted as an alias of
+//! `db`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::disasm::Opcode;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsmError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    OperandCount { line: usize, expected: usize, found: usize },
+    ImmediateWrite { line: usize },
+    InvalidOperand { line: usize, operand: String },
+    UndefinedSymbol { line: usize, symbol: String },
+    DuplicateSymbol { line: usize, symbol: String },
+    AddressMismatch { line: usize, expected: usize, found: usize },
+}
+
+impl AsmError {
+    /// The 1-based source line the error was found on.
+    pub fn line(&self) -> usize {
+        match *self {
+            AsmError::UnknownMnemonic { line, .. } |
+            AsmError::OperandCount { line, .. } |
+            AsmError::ImmediateWrite { line } |
+            AsmError::InvalidOperand { line, .. } |
+            AsmError::UndefinedSymbol { line, .. } |
+            AsmError::DuplicateSymbol { line, .. } |
+            AsmError::AddressMismatch { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AsmError::UnknownMnemonic { line, ref mnemonic } =>
+                write!(f, "line {}: unknown mnemonic {}", line, mnemonic),
+            AsmError::OperandCount { line, expected, found } =>
+                write!(f, "line {}: expected {} operands, found {}", line, expected, found),
+            AsmError::ImmediateWrite { line } =>
+                write!(f, "line {}: the written operand can't be immediate", line),
+            AsmError::InvalidOperand { line, ref operand } =>
+                write!(f, "line {}: invalid operand {}", line, operand),
+            AsmError::UndefinedSymbol { line, ref symbol } =>
+                write!(f, "line {}: undefined symbol {}", line, symbol),
+            AsmError::DuplicateSymbol { line, ref symbol } =>
+                write!(f, "line {}: {} is already defined", line, symbol),
+            AsmError::AddressMismatch { line, expected, found } =>
+                write!(f, "line {}: expected to be at address {}, but at {}", line, expected, found),
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+enum Statement<'a> {
+    Op { opcode: Opcode, operands: Vec<&'a str> },
+    Data(Vec<&'a str>),
+}
+
+impl<'a> Statement<'a> {
+    fn size(&self) -> usize {
+        match *self {
+            Statement::Op { opcode, .. } => opcode.arity() + 1,
+            Statement::Data(ref values) => values.iter().map(|value| string_literal(value).map(|s| s.len()).unwrap_or(1)).sum(),
+        }
+    }
+}
+
+fn opcode_from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+    (1..=9).chain(Some(99)).filter_map(Opcode::from_code).find(|opcode| opcode.mnemonic().eq_ignore_ascii_case(mnemonic))
+}
+
+pub(super) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().map(|ch| ch.is_ascii_alphabetic() || ch == '_').unwrap_or(false) && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+fn string_literal(s: &str) -> Option<&str> {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        Some(&s[1..s.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Strips the comment, taking care not to cut a string literal containing a `;`.
+pub(super) fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits operands on commas, leaving commas inside string literals alone.
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut in_string = false;
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                operands.push(s[start..i].trim());
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    if !s[start..].trim().is_empty() || !operands.is_empty() {
+        operands.push(s[start..].trim());
+    }
+    operands
+}
+
+pub(super) fn evaluate(expr: &str, symbols: &HashMap<&str, i64>, line: usize) -> Result<i64, AsmError> {
+    let invalid = || AsmError::InvalidOperand { line, operand: expr.to_string() };
+    let expr = expr.trim();
+    if expr.is_empty() {
+        return Err(invalid())
+    }
+
+    // split into signed terms, keeping a leading sign with the first one
+    let mut total: i64 = 0;
+    let mut term_start = 0;
+    let mut sign = 1;
+    let bytes = expr.as_bytes();
+    for i in 0..=bytes.len() {
+        let at_operator = i < bytes.len() && i > term_start && (bytes[i] == b'+' || bytes[i] == b'-');
+        if i < bytes.len() && !at_operator {
+            continue
+        }
+
+        let term = expr[term_start..i].trim();
+        let value = if let Ok(value) = term.parse::<i64>() {
+            value
+        } else if is_identifier(term) {
+            *symbols.get(term).ok_or_else(|| AsmError::UndefinedSymbol { line, symbol: term.to_string() })?
+        } else {
+            return Err(invalid())
+        };
+        total = value.checked_mul(sign).and_then(|value| total.checked_add(value)).ok_or_else(invalid)?;
+
+        if i < bytes.len() {
+            sign = if bytes[i] == b'+' { 1 } else { -1 };
+            term_start = i + 1;
+        }
+    }
+
+    Ok(total)
+}
+
+/// Assembles `source` into a program that can be loaded with `State::new`.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut symbols: HashMap<&str, i64> = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = 0;
+
+    // first pass: lay statements out and record where every label lands
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut rest = strip_comment(line).trim();
+
+        if let Some(eq) = rest.find('=') {
+            let name = rest[..eq].trim();
+            if is_identifier(name) {
+                let value = evaluate(&rest[eq + 1..], &symbols, line_number)?;
+                if symbols.insert(name, value).is_some() {
+                    return Err(AsmError::DuplicateSymbol { line: line_number, symbol: name.to_string() })
+                }
+                continue
+            }
+        }
+
+        while let Some(colon) = rest.find(':') {
+            let label = rest[..colon].trim();
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    return Err(AsmError::AddressMismatch { line: line_number, expected, found: addr })
+                }
+            } else if is_identifier(label) {
+                if symbols.insert(label, addr as i64).is_some() {
+                    return Err(AsmError::DuplicateSymbol { line: line_number, symbol: label.to_string() })
+                }
+            } else {
+                break
+            }
+            rest = rest[colon + 1..].trim();
+        }
+
+        if rest.is_empty() {
+            continue
+        }
+
+        let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+            Some(space) => (&rest[..space], split_operands(&rest[space..])),
+            None => (rest, Vec::new()),
+        };
+
+        let statement = if mnemonic.eq_ignore_ascii_case("db") || mnemonic.eq_ignore_ascii_case("data") {
+            Statement::Data(operands)
+        } else {
+            let opcode = opcode_from_mnemonic(mnemonic).ok_or_else(|| AsmError::UnknownMnemonic { line: line_number, mnemonic: mnemonic.to_string() })?;
+            if operands.len() != opcode.arity() {
+                return Err(AsmError::OperandCount { line: line_number, expected: opcode.arity(), found: operands.len() })
+            }
+            Statement::Op { opcode, operands }
+        };
+
+        addr += statement.size();
+        statements.push((line_number, statement));
+    }
+
+    // second pass: every symbol is known, encode
+    let mut program = Vec::with_capacity(addr);
+    for (line, statement) in statements {
+        match statement {
+            Statement::Op { opcode, operands } => {
+                let mut op = opcode.code();
+                let mut params = Vec::with_capacity(operands.len());
+
+                for (i, operand) in operands.iter().enumerate() {
+                    let (mode, expr) = if let Some(expr) = operand.strip_prefix('#') {
+                        (1, expr)
+                    } else if let Some(expr) = operand.strip_prefix('@') {
+                        (2, expr)
+                    } else {
+                        (0, *operand)
+                    };
+
+                    if mode == 1 && opcode.written_param() == Some(i) {
+                        return Err(AsmError::ImmediateWrite { line })
+                    }
+
+                    op += mode * 10i64.pow(i as u32 + 2);
+                    params.push(evaluate(expr, &symbols, line)?);
+                }
+
+                program.push(op);
+                program.extend(params);
+            },
+            Statement::Data(values) => {
+                for value in values {
+                    match string_literal(value) {
+                        Some(s) => program.extend(s.bytes().map(|b| b as i64)),
+                        None => program.push(evaluate(value, &symbols, line)?),
+                    }
+                }
+            },
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{listing, State, Status};
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            x = 1
+                    arb #frame
+            loop:   in @x       ; read a value
+                    jz @x, #done
+                    mul @x, #2, @x
+                    out @x
+                    jnz #1, #loop
+            done:   halt
+            frame:  db 0, 0, \"a;b\"
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(program, vec![109,17,203,1,1206,1,16,21202,1,2,1,204,1,1105,1,2,99,0,0,97,59,98]);
+
+        let mut state = State::new(&program);
+        state.push_input(21);
+        assert_eq!(state.run(), Ok(Status::Output(42)));
+        state.push_input(0);
+        assert_eq!(state.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(assemble("add a+1, #-3, b-a\na: halt\nb: DATA 7, -1").unwrap(), vec![1001,5,-3,1,99,7,-1]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(assemble("nop"), Err(AsmError::UnknownMnemonic { line: 1, mnemonic: "nop".to_string() }));
+        assert_eq!(assemble("\nadd 1, 2"), Err(AsmError::OperandCount { line: 2, expected: 3, found: 2 }));
+        assert_eq!(assemble("in #3"), Err(AsmError::ImmediateWrite { line: 1 }));
+        assert_eq!(assemble("out nowhere"), Err(AsmError::UndefinedSymbol { line: 1, symbol: "nowhere".to_string() }));
+        assert_eq!(assemble("out 1+"), Err(AsmError::InvalidOperand { line: 1, operand: "1+".to_string() }));
+        assert_eq!(assemble("db 9223372036854775807+1"), Err(AsmError::InvalidOperand { line: 1, operand: "9223372036854775807+1".to_string() }));
+        assert_eq!(assemble("db -9223372036854775807-2"), Err(AsmError::InvalidOperand { line: 1, operand: "-9223372036854775807-2".to_string() }));
+        assert_eq!(assemble("db -9223372036854775807-1"), Ok(vec![i64::MIN]));
+        assert_eq!(assemble("a: halt\na: halt"), Err(AsmError::DuplicateSymbol { line: 2, symbol: "a".to_string() }));
+        assert_eq!(assemble("halt\n0002: halt"), Err(AsmError::AddressMismatch { line: 2, expected: 2, found: 1 }));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for input in [include_str!("../../inputs/09.txt"), include_str!("../../inputs/13.txt"), include_str!("../../inputs/17.txt")].iter() {
+            let program = input.split(',').map(|x| x.trim().parse().unwrap()).collect::<Vec<i64>>();
+            assert_eq!(assemble(&listing(&program)), Ok(program));
+        }
+    }
+}
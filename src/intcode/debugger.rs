@@ -0,0 +1,454 @@
+//! Interactive debugger for Intcode programs, driving a synchronous `State` one instruction at a
+//! time. Commands:
+//!
+//! ```text
+//! step [n]            s    execute n instructions (default 1)
+//! continue            c    run until a breakpoint, a watched cell changes, input runs out or halt
+//! break <addr>        b    set a breakpoint
+//! delete <addr>       d    remove a breakpoint
+//! watch <addr>        w    stop whenever the cell at <addr> changes
+//! unwatch <addr>           stop watching a cell
+//! registers           r    print pc and relative_base along with the next instruction
+//! dump <addr> [count] x    print count cells starting at addr (default 16)
+//! list [addr] [count] l    disassemble count instructions from addr (default: pc, 8)
+//! input <v> [v...]    i    queue values for input instructions
+//! save <file>              write a snapshot of the machine to file
+//! load <file>              resume the machine from a snapshot file
+//! help                h
+//! quit                q
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::disasm::{decode, Instruction};
+use super::{exec_op, Next, Snapshot, State};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Break(usize),
+    Delete(usize),
+    Watch(usize),
+    Unwatch(usize),
+    Registers,
+    Dump(usize, usize),
+    List(Option<usize>, usize),
+    Input(Vec<i64>),
+    Save(String),
+    Load(String),
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().ok_or_else(|| "empty command".to_string())?;
+        let args = words.collect::<Vec<_>>();
+
+        fn number<T: FromStr>(arg: Option<&&str>, what: &str) -> Result<Option<T>, String> {
+            arg.map(|arg| arg.parse().map_err(|_| format!("invalid {}: {}", what, arg))).transpose()
+        }
+        let file = |i: usize| args.get(i).map(|s| s.to_string()).ok_or_else(|| format!("{} expects a file", name));
+        let addr = |i: usize| number::<usize>(args.get(i), "address")?.ok_or_else(|| format!("{} expects an address", name));
+
+        match name {
+            "step" | "s" => Ok(Command::Step(number(args.first(), "count")?.unwrap_or(1))),
+            "continue" | "c" => Ok(Command::Continue),
+            "break" | "b" => Ok(Command::Break(addr(0)?)),
+            "delete" | "d" => Ok(Command::Delete(addr(0)?)),
+            "watch" | "w" => Ok(Command::Watch(addr(0)?)),
+            "unwatch" => Ok(Command::Unwatch(addr(0)?)),
+            "registers" | "r" => Ok(Command::Registers),
+            "dump" | "x" => Ok(Command::Dump(addr(0)?, number(args.get(1), "count")?.unwrap_or(16))),
+            "list" | "l" => Ok(Command::List(number(args.first(), "address")?, number(args.get(1), "count")?.unwrap_or(8))),
+            "input" | "i" => {
+                let values = args.iter().map(|arg| arg.parse().map_err(|_| format!("invalid input: {}", arg))).collect::<Result<Vec<i64>, String>>()?;
+                if values.is_empty() {
+                    return Err("input expects at least one value".to_string())
+                }
+                Ok(Command::Input(values))
+            },
+            "save" => Ok(Command::Save(file(0)?)),
+            "load" => Ok(Command::Load(file(0)?)),
+            "help" | "h" => Ok(Command::Help),
+            "quit" | "q" => Ok(Command::Quit),
+            _ => Err(format!("unknown command: {}", name)),
+        }
+    }
+}
+
+/// Why the machine stopped running.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Stop {
+    Breakpoint,
+    Watch,
+    NeedsInput,
+    Halted,
+    Error,
+}
+
+pub struct Debugger {
+    pub state: State,
+    breakpoints: BTreeSet<usize>,
+    watches: BTreeMap<usize, i64>,
+    halted: bool,
+}
+
+impl Debugger {
+    pub fn new(program: &[i64]) -> Self {
+        Debugger{state: State::new(program), breakpoints: BTreeSet::new(), watches: BTreeMap::new(), halted: false}
+    }
+
+    /// Executes a command, writing its report to `out`. Returns false once the session should end.
+    pub fn execute<W: Write>(&mut self, command: &Command, out: &mut W) -> io::Result<bool> {
+        match *command {
+            Command::Step(count) => {
+                for _ in 0..count {
+                    if let Some(stop) = self.step(out)? {
+                        if stop != Stop::Watch {
+                            break
+                        }
+                    }
+                }
+                self.print_next(out)?;
+            },
+            Command::Continue => {
+                // always move off the current instruction, even if it's a breakpoint
+                let mut stop = self.step(out)?;
+                while stop.is_none() {
+                    if self.pc().map(|pc| self.breakpoints.contains(&pc)).unwrap_or(false) {
+                        writeln!(out, "breakpoint at {}", self.state.pc)?;
+                        stop = Some(Stop::Breakpoint);
+                    } else {
+                        stop = self.step(out)?;
+                    }
+                }
+                self.print_next(out)?;
+            },
+            Command::Break(addr) => {
+                self.breakpoints.insert(addr);
+                writeln!(out, "breakpoint set at {}", addr)?;
+            },
+            Command::Delete(addr) => {
+                if self.breakpoints.remove(&addr) {
+                    writeln!(out, "breakpoint at {} removed", addr)?;
+                } else {
+                    writeln!(out, "no breakpoint at {}", addr)?;
+                }
+            },
+            Command::Watch(addr) => {
+                let value = self.state.memory.get(addr);
+                self.watches.insert(addr, value);
+                writeln!(out, "watching [{}] = {}", addr, value)?;
+            },
+            Command::Unwatch(addr) => {
+                if self.watches.remove(&addr).is_some() {
+                    writeln!(out, "stopped watching [{}]", addr)?;
+                } else {
+                    writeln!(out, "[{}] isn't watched", addr)?;
+                }
+            },
+            Command::Registers => {
+                writeln!(out, "pc: {}, relative_base: {}, pending inputs: {:?}", self.state.pc, self.state.relative_base, self.state.inputs)?;
+                self.print_next(out)?;
+            },
+            Command::Dump(start, count) => {
+                if count == 0 {
+                    return Ok(true)
+                }
+                let last = match start.checked_add(count - 1) {
+                    Some(last) => last,
+                    None => {
+                        writeln!(out, "error: dump runs past the last address")?;
+                        return Ok(true)
+                    },
+                };
+                for row_start in (start..=last).step_by(8) {
+                    let row_last = row_start.saturating_add(7).min(last);
+                    let cells = (row_start..=row_last).map(|addr| format!("{:>8}", self.state.memory.get(addr))).collect::<String>();
+                    writeln!(out, "{:04}:{}", row_start, cells)?;
+                }
+            },
+            Command::List(addr, count) => {
+                let mut addr = match addr.or_else(|| self.pc()) {
+                    Some(addr) => addr,
+                    None => return Ok(true),
+                };
+                for _ in 0..count {
+                    let instruction = self.decode(addr);
+                    writeln!(out, "{} {:04}: {}", if Some(addr) == self.pc() { "=>" } else { "  " }, addr, instruction)?;
+                    addr = match addr.checked_add(instruction.size()) {
+                        Some(next) => next,
+                        None => {
+                            writeln!(out, "error: list runs past the last address")?;
+                            break
+                        },
+                    };
+                }
+            },
+            Command::Input(ref values) => {
+                values.iter().for_each(|value| self.state.push_input(*value));
+                writeln!(out, "queued {} input(s)", values.len())?;
+            },
+            Command::Save(ref path) => {
+                match self.state.snapshot().save(path) {
+                    Ok(()) => writeln!(out, "saved to {}", path)?,
+                    Err(e) => writeln!(out, "error: {}", e)?,
+                }
+            },
+            Command::Load(ref path) => {
+                match Snapshot::load(path) {
+                    Ok(snapshot) => {
+                        self.state.restore(&snapshot);
+                        self.halted = false;
+                        for (addr, value) in self.watches.iter_mut() {
+                            *value = self.state.memory.get(*addr);
+                        }
+                        writeln!(out, "loaded {}", path)?;
+                        self.print_next(out)?;
+                    },
+                    Err(e) => writeln!(out, "error: {}", e)?,
+                }
+            },
+            Command::Help => {
+                writeln!(out, "commands: step [n], continue, break <addr>, delete <addr>, watch <addr>, unwatch <addr>, registers, dump <addr> [count], list [addr] [count], input <v>..., save <file>, load <file>, quit")?;
+            },
+            Command::Quit => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Reads commands from `input` until it runs out or `quit` is read. With `echo`, every command
+    /// is written back before its report, which makes transcripts of scripted sessions readable.
+    pub fn run_script<R: BufRead, W: Write>(&mut self, input: R, out: &mut W, echo: bool) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            if echo {
+                writeln!(out, "> {}", line)?;
+            }
+
+            match line.parse::<Command>() {
+                Ok(command) => {
+                    if !self.execute(&command, out)? {
+                        break
+                    }
+                },
+                Err(e) => writeln!(out, "{}", e)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pc(&self) -> Option<usize> {
+        if self.state.pc >= 0 {
+            Some(self.state.pc as usize)
+        } else {
+            None
+        }
+    }
+
+    fn decode(&self, addr: usize) -> Instruction {
+        // an instruction spans at most 4 cells, and those past the end of memory, or past the
+        // last address, read as 0
+        let cells = (0..4).map(|i| addr.checked_add(i).map(|addr| self.state.memory.get(addr)).unwrap_or(0)).collect::<Vec<_>>();
+        match decode(&cells, 0) {
+            Some(Instruction::Op { opcode, params, .. }) => Instruction::Op { addr, opcode, params },
+            _ => Instruction::Data { addr, value: cells[0] },
+        }
+    }
+
+    fn print_next<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self.pc() {
+            Some(pc) if !self.halted => writeln!(out, "=> {:04}: {}", pc, self.decode(pc)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Executes a single instruction, reporting outputs and changes to watched cells. Returns why
+    /// the machine has to stop, if it does.
+    fn step<W: Write>(&mut self, out: &mut W) -> io::Result<Option<Stop>> {
+        if self.halted {
+            writeln!(out, "halted")?;
+            return Ok(Some(Stop::Halted))
+        }
+
+        let mut stop = match exec_op(&mut self.state) {
+            Ok(Next::Continue) => None,
+            Ok(Next::Output(value)) => {
+                writeln!(out, "output: {}", value)?;
+                None
+            },
+            Ok(Next::NeedsInput) => {
+                writeln!(out, "waiting for input at {}", self.state.pc)?;
+                Some(Stop::NeedsInput)
+            },
+            Ok(Next::Exit(_)) => {
+                self.halted = true;
+                writeln!(out, "halted")?;
+                Some(Stop::Halted)
+            },
+            Err(e) => {
+                writeln!(out, "error: {}", e)?;
+                Some(Stop::Error)
+            },
+        };
+
+        for (addr, last) in self.watches.iter_mut() {
+            let value = self.state.memory.get(*addr);
+            if value != *last {
+                writeln!(out, "watch [{}]: {} -> {}", addr, last, value)?;
+                *last = value;
+                stop = stop.or(Some(Stop::Watch));
+            }
+        }
+
+        Ok(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    fn transcript(program: &[i64], script: &str) -> String {
+        let mut debugger = Debugger::new(program);
+        let mut out = Vec::new();
+        debugger.run_script(script.as_bytes(), &mut out, true).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("s".parse(), Ok(Command::Step(1)));
+        assert_eq!("step 10".parse(), Ok(Command::Step(10)));
+        assert_eq!("x 4".parse(), Ok(Command::Dump(4, 16)));
+        assert_eq!("list".parse(), Ok(Command::List(None, 8)));
+        assert_eq!("i 1 -2".parse(), Ok(Command::Input(vec![1, -2])));
+        assert_eq!("break".parse::<Command>(), Err("break expects an address".to_string()));
+        assert_eq!("jump 4".parse::<Command>(), Err("unknown command: jump".to_string()));
+        assert_eq!("save out.snap".parse(), Ok(Command::Save("out.snap".to_string())));
+        assert_eq!("load".parse::<Command>(), Err("load expects a file".to_string()));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("icdb-{}.snap", std::process::id()));
+        let path = path.to_str().unwrap();
+        let program = [3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0];
+
+        let saved = transcript(&program, &format!("input 21\nbreak 9\nc\nsave {}\n", path));
+        assert!(saved.ends_with(&format!("breakpoint at 9\n=> 0009: out 15\n> save {}\nsaved to {}\n", path, path)));
+
+        let loaded = transcript(&[], &format!("load {}\nc\ninput 5\nc\n", path));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded, format!("\
+> load {}
+loaded {}
+=> 0009: out 15
+> c
+output: 42
+waiting for input at 0
+=> 0000: in 15
+> input 5
+queued 1 input(s)
+> c
+output: 10
+waiting for input at 0
+=> 0000: in 15
+", path, path));
+    }
+
+    #[test]
+    fn test_last_address() {
+        assert_eq!(transcript(&[], "x 18446744073709551614 2\nx 18446744073709551614 3\nl 18446744073709551614 3"), "\
+> x 18446744073709551614 2
+18446744073709551614:       0       0
+> x 18446744073709551614 3
+error: dump runs past the last address
+> l 18446744073709551614 3
+   18446744073709551614: DATA 0
+   18446744073709551615: DATA 0
+error: list runs past the last address
+");
+    }
+
+    #[test]
+    fn test_session() {
+        let program = assemble("
+                    in 15
+                    jz 15, #14
+            mul:    mul 15, #2, 15
+                    out 15
+                    jnz #1, #0
+                    halt
+                    db 0
+        ").unwrap();
+
+        assert_eq!(transcript(&program, "
+            r
+            c
+            input 21 0
+            break 5
+            watch 15
+            c
+            c
+            c
+            c
+            delete 5
+            x 14 3
+            c
+            c
+            q
+            s
+        "), "\
+> r
+pc: 0, relative_base: 0, pending inputs: []
+=> 0000: in 15
+> c
+waiting for input at 0
+=> 0000: in 15
+> input 21 0
+queued 2 input(s)
+> break 5
+breakpoint set at 5
+> watch 15
+watching [15] = 0
+> c
+watch [15]: 0 -> 21
+=> 0002: jz 15, #14
+> c
+breakpoint at 5
+=> 0005: mul 15, #2, 15
+> c
+watch [15]: 21 -> 42
+=> 0009: out 15
+> c
+output: 42
+watch [15]: 42 -> 0
+=> 0002: jz 15, #14
+> delete 5
+breakpoint at 5 removed
+> x 14 3
+0014:      99       0       0
+> c
+halted
+> c
+halted
+> q
+");
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+
+/// An instruction parameter along with the mode it is read in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Param {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Param {
+    fn decode(mode: i64, value: i64) -> Option<Param> {
+        match mode {
+            0 => Some(Param::Position(value)),
+            1 => Some(Param::Immediate(value)),
+            2 => Some(Param::Relative(value)),
+            _ => None,
+        }
+    }
+
+    pub fn mode(&self) -> i64 {
+        match *self {
+            Param::Position(_) => 0,
+            Param::Immediate(_) => 1,
+            Param::Relative(_) => 2,
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        match *self {
+            Param::Position(value) | Param::Immediate(value) | Param::Relative(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Param::Position(value) => write!(f, "{}", value),
+            Param::Immediate(value) => write!(f, "#{}", value),
+            Param::Relative(value) => write!(f, "@{}", value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Opcode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    Arb,
+    Halt,
+}
+
+impl Opcode {
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mul),
+            3 => Some(Opcode::In),
+            4 => Some(Opcode::Out),
+            5 => Some(Opcode::Jnz),
+            6 => Some(Opcode::Jz),
+            7 => Some(Opcode::Lt),
+            8 => Some(Opcode::Eq),
+            9 => Some(Opcode::Arb),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> i64 {
+        match *self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jnz => 5,
+            Opcode::Jz => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match *self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::In => "in",
+            Opcode::Out => "out",
+            Opcode::Jnz => "jnz",
+            Opcode::Jz => "jz",
+            Opcode::Lt => "lt",
+            Opcode::Eq => "eq",
+            Opcode::Arb => "arb",
+            Opcode::Halt => "halt",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match *self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jnz | Opcode::Jz => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    pub fn written_param(&self) -> Option<usize> {
+        match *self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => Some(2),
+            Opcode::In => Some(0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Instruction {
+    Op { addr: usize, opcode: Opcode, params: Vec<Param> },
+    /// A cell that doesn't decode to a valid instruction.
+    Data { addr: usize, value: i64 },
+}
+
+impl Instruction {
+    pub fn addr(&self) -> usize {
+        match *self {
+            Instruction::Op { addr, .. } | Instruction::Data { addr, .. } => addr,
+        }
+    }
+
+    /// Number of memory cells the instruction spans.
+    pub fn size(&self) -> usize {
+        match *self {
+            Instruction::Op { ref params, .. } => params.len() + 1,
+            Instruction::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Op { opcode, ref params, .. } => {
+                write!(f, "{}", opcode.mnemonic())?;
+                for (i, param) in params.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { " " } else { ", " }, param)?;
+                }
+                Ok(())
+            },
+            Instruction::Data { value, .. } => write!(f, "DATA {}", value),
+        }
+    }
+}
+
+/// Decodes the instruction at `addr`, returning `None` if the cell holds an unknown opcode, a mode
+/// that doesn't exist, a write parameter in immediate mode, or if the program ends before all of
+/// its parameters.
+pub fn decode(program: &[i64], addr: usize) -> Option<Instruction> {
+    let op = *program.get(addr)?;
+    if op < 0 {
+        return None
+    }
+
+    let opcode = Opcode::from_code(op % 100)?;
+    // leftover mode digits past the last parameter mean this isn't an instruction
+    if op / 10i64.pow(opcode.arity() as u32 + 2) != 0 {
+        return None
+    }
+
+    let mut params = Vec::with_capacity(opcode.arity());
+    for i in 0..opcode.arity() {
+        let mode = (op / 10i64.pow(i as u32 + 2)) % 10;
+        let param = Param::decode(mode, *program.get(addr + i + 1)?)?;
+        if opcode.written_param() == Some(i) && mode == 1 {
+            return None
+        }
+        params.push(param);
+    }
+
+    Some(Instruction::Op { addr, opcode, params })
+}
+
+/// Decodes the whole program with a linear sweep. Cells that can't be decoded become `DATA`
+/// directives and decoding resumes on the next cell, so data that happens to look like code
+/// (or the other way around) can still be mislabelled.
+pub fn disassemble(program: &[i64]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut addr = 0;
+
+    while addr < program.len() {
+        let instruction = decode(program, addr).unwrap_or(Instruction::Data { addr, value: program[addr] });
+        addr += instruction.size();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Renders the disassembly, one instruction per line, prefixed by its address and followed by the
+/// raw cells it was decoded from.
+pub fn listing(program: &[i64]) -> String {
+    let mut out = String::new();
+
+    for instruction in disassemble(program) {
+        let addr = instruction.addr();
+        let raw = program[addr..addr + instruction.size()].iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+        let line = format!("{:04}: {}", addr, instruction);
+        out.push_str(&format!("{:<40}; {}\n", line, raw));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(&[1002,4,3,4,33][..], 0), Some(Instruction::Op { addr: 0, opcode: Opcode::Mul, params: vec![Param::Position(4), Param::Immediate(3), Param::Position(4)] }));
+        assert_eq!(decode(&[109,19][..], 0), Some(Instruction::Op { addr: 0, opcode: Opcode::Arb, params: vec![Param::Immediate(19)] }));
+        assert_eq!(decode(&[204,-34][..], 0), Some(Instruction::Op { addr: 0, opcode: Opcode::Out, params: vec![Param::Relative(-34)] }));
+
+        // write in immediate mode, unknown mode, stray mode digit, unknown opcode, truncated
+        assert_eq!(decode(&[11101,1,1,1][..], 0), None);
+        assert_eq!(decode(&[301,1,1,1][..], 0), None);
+        assert_eq!(decode(&[1099][..], 0), None);
+        assert_eq!(decode(&[42][..], 0), None);
+        assert_eq!(decode(&[1,2,3][..], 0), None);
+    }
+
+    #[test]
+    fn test_listing() {
+        let program = [3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,-7];
+        assert_eq!(listing(&program[..]), "\
+0000: in 15                             ; 3,15
+0002: jz 15, #14                        ; 1006,15,14
+0005: mul 15, #2, 15                    ; 1002,15,2,15
+0009: out 15                            ; 4,15
+0011: jnz #1, #0                        ; 1105,1,0
+0014: halt                              ; 99
+0015: DATA -7                           ; -7
+");
+    }
+}
@@ -0,0 +1,70 @@
+//! Reader for the text files snapshots and replays are saved as: a `header version` line, then
+//! one `name value` line per field, in a fixed order.
+
+use std::io::{self, BufRead, Lines};
+use std::str::FromStr;
+
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parses the `value` of the field `name`.
+pub(crate) fn parse<T: FromStr>(name: &str, value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| invalid(format!("invalid {}: {}", name, value)))
+}
+
+pub(crate) struct Fields<R> {
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> Fields<R> {
+    /// Checks the file starts with `header` at `version`, `kind` naming the file in errors.
+    pub(crate) fn open(input: R, header: &str, version: u32, kind: &str) -> io::Result<Self> {
+        let mut fields = Fields{lines: input.lines()};
+        let found = fields.next(header)?;
+        if found != version.to_string() {
+            return Err(invalid(format!("unsupported {} version: {}", kind, found)))
+        }
+        Ok(fields)
+    }
+
+    /// The value of the next line, which has to be the field `name`.
+    pub(crate) fn next(&mut self, name: &str) -> io::Result<String> {
+        let line = self.lines.next().ok_or_else(|| invalid(format!("missing {}", name)))??;
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(key), value) if key == name => Ok(value.unwrap_or("").trim().to_string()),
+            _ => Err(invalid(format!("expected {}, found: {}", name, line))),
+        }
+    }
+
+    /// The next field, `name`, parsed.
+    pub(crate) fn parse<T: FromStr>(&mut self, name: &str) -> io::Result<T> {
+        parse(name, &self.next(name)?)
+    }
+
+    /// The next field, `name`, as a comma separated list.
+    pub(crate) fn list<T: FromStr>(&mut self, name: &str) -> io::Result<Vec<T>> {
+        self.next(name)?.split(',').filter(|v| !v.is_empty()).map(|v| parse(name, v)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let open = |s: &'static str| Fields::open(s.as_bytes(), "file", 1, "test");
+        let mut fields = open("file 1\ncount 3\nempty\nvalues 1,,2\n").unwrap();
+        assert_eq!(fields.parse::<usize>("count").unwrap(), 3);
+        assert_eq!(fields.next("empty").unwrap(), "");
+        assert_eq!(fields.list::<i64>("values").unwrap(), vec![1, 2]);
+        assert_eq!(fields.next("more").unwrap_err().to_string(), "missing more");
+
+        assert_eq!(open("file 2\n").err().unwrap().to_string(), "unsupported test version: 2");
+        assert_eq!(open("").err().unwrap().to_string(), "missing file");
+        let mut fields = open("file 1\ncount x\n").unwrap();
+        assert_eq!(fields.parse::<usize>("count").unwrap_err().to_string(), "invalid count: x");
+    }
+}
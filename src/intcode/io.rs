@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+/// Returned by a source or a sink once nobody is left on the other end.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Closed;
+
+/// Where a machine reads its inputs from once its queued inputs run out.
+pub trait Input {
+    /// Returns the next value, `None` if there isn't one yet, which suspends the machine with
+    /// `NeedsInput`, or `Closed` if there will never be one.
+    fn read(&mut self) -> Result<Option<i64>, Closed>;
+}
+
+/// Where a machine sends its outputs instead of handing them back from `run`.
+pub trait Output {
+    /// `Closed` makes the machine halt, as nobody is listening anymore.
+    fn write(&mut self, value: i64) -> Result<(), Closed>;
+}
+
+impl Input for VecDeque<i64> {
+    fn read(&mut self) -> Result<Option<i64>, Closed> {
+        Ok(self.pop_front())
+    }
+}
+
+/// Blocks until a value is sent.
+impl Input for Receiver<i64> {
+    fn read(&mut self) -> Result<Option<i64>, Closed> {
+        self.recv().map(Some).map_err(|_| Closed)
+    }
+}
+
+impl<F: FnMut() -> Option<i64>> Input for F {
+    fn read(&mut self) -> Result<Option<i64>, Closed> {
+        Ok(self())
+    }
+}
+
+impl Output for Sender<i64> {
+    fn write(&mut self, value: i64) -> Result<(), Closed> {
+        self.send(value).map_err(|_| Closed)
+    }
+}
+
+impl Output for SyncSender<i64> {
+    fn write(&mut self, value: i64) -> Result<(), Closed> {
+        self.send(value).map_err(|_| Closed)
+    }
+}
+
+impl<F: FnMut(i64)> Output for F {
+    fn write(&mut self, value: i64) -> Result<(), Closed> {
+        self(value);
+        Ok(())
+    }
+}
+
+/// Reads or writes one value per line, e.g. to talk to a machine over a socket. Blank lines are
+/// skipped; reaching the end of the stream, an error or anything that isn't a number closes it.
+pub struct Text<T>(pub T);
+
+impl<R: BufRead> Input for Text<R> {
+    fn read(&mut self) -> Result<Option<i64>, Closed> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.0.read_line(&mut line) {
+                Ok(0) | Err(_) => return Err(Closed),
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => return line.trim().parse().map(Some).map_err(|_| Closed),
+            }
+        }
+    }
+}
+
+impl<W: Write> Output for Text<W> {
+    fn write(&mut self, value: i64) -> Result<(), Closed> {
+        writeln!(self.0, "{}", value).and_then(|_| self.0.flush()).map_err(|_| Closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let mut input = Text(&b"12\n\n-3\nx\n"[..]);
+        assert_eq!(input.read(), Ok(Some(12)));
+        assert_eq!(input.read(), Ok(Some(-3)));
+        assert_eq!(input.read(), Err(Closed));
+        assert_eq!(input.read(), Err(Closed));
+
+        let mut output = Text(Vec::new());
+        output.write(4).unwrap();
+        output.write(-2).unwrap();
+        assert_eq!(output.0, b"4\n-2\n");
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use super::Memory;
+
+/// Limits on how long `run` keeps a machine going, none by default. Hitting one is reported as
+/// an error, after which `run` can be called again to resume, once the limit is raised.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Limits {
+    /// Most instructions the machine executes in total, as counted by `steps`.
+    pub steps: Option<usize>,
+    /// Longest a single call to `run` can take. Only checked every thousand instructions or so.
+    pub time: Option<Duration>,
+    /// Whether to stop when the machine comes back to the exact same state without reading or
+    /// writing anything in between, as it would then go around forever.
+    pub loops: bool,
+}
+
+/// How often, in instructions, the clock is checked.
+pub(super) const CLOCK_INTERVAL: usize = 1024;
+
+/// Loop detection, with Brent's algorithm: the state is saved after 1, 2, 4, 8... instructions
+/// without I/O, and compared to every state after it. Comparing whole memories every instruction
+/// would be too slow, so they're compared through a hash kept up to date on every write.
+#[derive(Debug)]
+pub(super) struct Watch {
+    hash: u64,
+    saved: Option<(i64, i64, u64)>,
+    steps: usize,
+    next_save: usize,
+    /// Range of pcs seen since the state was saved.
+    low: i64,
+    high: i64,
+}
+
+/// Hash of a single cell. Memory is summed up cell by cell, so that a write only needs to swap
+/// the old cell's hash for the new one's, and zeros hash to nothing, so growing memory doesn't
+/// change the sum.
+fn cell(addr: usize, value: i64) -> u64 {
+    if value == 0 {
+        return 0
+    }
+
+    // splitmix64's finalizer
+    let mut x = (addr as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (value as u64);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl Watch {
+    pub(super) fn new(memory: &Memory) -> Self {
+        let hash = memory.as_slice().iter().enumerate().fold(0u64, |hash, (addr, &value)| hash.wrapping_add(cell(addr, value)));
+        Watch{hash, saved: None, steps: 0, next_save: 1, low: 0, high: 0}
+    }
+
+    pub(super) fn wrote(&mut self, addr: usize, old: i64, new: i64) {
+        self.hash = self.hash.wrapping_sub(cell(addr, old)).wrapping_add(cell(addr, new));
+    }
+
+    /// Input or output happened, the machine isn't stuck.
+    pub(super) fn io(&mut self) {
+        self.saved = None;
+        self.steps = 0;
+        self.next_save = 1;
+    }
+
+    /// Records the state about to execute, returning the range of pcs of the loop it's in if it
+    /// was seen before.
+    pub(super) fn step(&mut self, pc: i64, relative_base: i64) -> Option<(i64, i64)> {
+        let state = (pc, relative_base, self.hash);
+        self.low = self.low.min(pc);
+        self.high = self.high.max(pc);
+        if self.saved == Some(state) {
+            return Some((self.low, self.high))
+        }
+
+        self.steps += 1;
+        if self.steps == self.next_save {
+            self.saved = Some(state);
+            self.next_save *= 2;
+            self.low = pc;
+            self.high = pc;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{assemble, IntcodeError, State, Status};
+
+    const FOREVER: &[i64] = &[1105,1,0];
+
+    #[test]
+    fn test_steps() {
+        let mut state = State::new(FOREVER);
+        state.limits.steps = Some(100);
+        assert_eq!(state.run(), Err(IntcodeError::StepLimit { pc: 0, instruction: 1105, steps: 100 }));
+
+        // outputs its input doubled
+        let mut state = State::new(&[3,9,1002,9,2,9,4,9,99,0]);
+        state.limits.steps = Some(2);
+        state.push_input(21);
+        assert_eq!(state.run(), Err(IntcodeError::StepLimit { pc: 6, instruction: 4, steps: 2 }));
+        state.limits.steps = None;
+        assert_eq!(state.run(), Ok(Status::Output(42)));
+    }
+
+    #[test]
+    fn test_time() {
+        let mut state = State::new(FOREVER);
+        state.limits.time = Some(Duration::from_millis(10));
+        assert_eq!(state.run(), Err(IntcodeError::TimeLimit { pc: 0, instruction: 1105, limit: Duration::from_millis(10) }));
+        assert!(state.steps >= CLOCK_INTERVAL);
+    }
+
+    #[test]
+    fn test_loops() {
+        let mut state = State::new(FOREVER);
+        state.limits.loops = true;
+        assert_eq!(state.run(), Err(IntcodeError::InfiniteLoop { pc: 0, instruction: 1105, start: 0, end: 0 }));
+
+        // flips a cell back and forth, after a countdown that doesn't repeat
+        let mut state = State::new(&assemble("
+                    out #1
+            count:  add n, #-1, n
+                    jnz n, #count
+            flip:   eq x, #0, x
+                    jz #0, #flip
+            n:      db 1000
+            x:      db 0
+        ").unwrap());
+        state.limits.loops = true;
+        assert_eq!(state.run(), Ok(Status::Output(1)));
+        assert_eq!(state.run(), Err(IntcodeError::InfiniteLoop { pc: 13, instruction: 1106, start: 9, end: 13 }));
+        assert!(state.steps < 3000);
+
+        // counts forever, never in the same state twice
+        let mut state = State::new(&assemble("loop: add n, #1, n\njz #0, #loop\nn: db 0").unwrap());
+        state.limits.loops = true;
+        state.limits.steps = Some(10000);
+        assert_eq!(state.run(), Err(IntcodeError::StepLimit { pc: 0, instruction: 1001, steps: 10000 }));
+    }
+}
@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::fmt;
+
+/// Addresses from here on can't be written to: memory is a plain vector, and a stray write to a
+/// huge address would otherwise try to allocate all of it.
+pub const MAX_LEN: usize = 1 << 24;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MemoryError {
+    AddressTooLarge { addr: usize },
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MemoryError::AddressTooLarge { addr } =>
+                write!(f, "address {} is past the end of memory ({} cells)", addr, MAX_LEN),
+        }
+    }
+}
+
+impl Error for MemoryError {}
+
+/// Contiguous machine memory. Cells that were never written read as 0, and writes past the end
+/// grow the backing vector, up to `MAX_LEN` cells.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+    cells: Vec<i64>,
+}
+
+impl Clone for Memory {
+    fn clone(&self) -> Self {
+        Memory{cells: self.cells.clone()}
+    }
+
+    /// Reuses the backing vector, which makes resetting a machine to a snapshot cheap.
+    fn clone_from(&mut self, source: &Self) {
+        self.cells.clone_from(&source.cells);
+    }
+}
+
+impl Memory {
+    pub fn get(&self, addr: usize) -> i64 {
+        *self.cells.get(addr).unwrap_or(&0)
+    }
+
+    pub fn set(&mut self, addr: usize, value: i64) -> Result<(), MemoryError> {
+        Memory::check(addr)?;
+        if addr >= self.cells.len() {
+            self.cells.resize(addr + 1, 0);
+        }
+
+        self.cells[addr] = value;
+        Ok(())
+    }
+
+    /// Whether `addr` can be written to.
+    pub fn check(addr: usize) -> Result<(), MemoryError> {
+        if addr >= MAX_LEN {
+            return Err(MemoryError::AddressTooLarge { addr })
+        }
+        Ok(())
+    }
+
+    /// Number of cells currently backed by the vector; every address past it reads as 0.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[i64] {
+        &self.cells
+    }
+}
+
+impl From<&[i64]> for Memory {
+    fn from(program: &[i64]) -> Self {
+        Memory{cells: program.to_vec()}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grows_on_write() {
+        let mut memory = Memory::from(&[1, 2, 3][..]);
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.get(1000), 0);
+        assert_eq!(memory.len(), 3);
+
+        memory.set(10, 42).unwrap();
+        assert_eq!(memory.len(), 11);
+        assert_eq!(memory.get(10), 42);
+        assert_eq!(memory.get(5), 0);
+        assert_eq!(&memory.as_slice()[..3], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_address_too_large() {
+        let mut memory = Memory::default();
+        assert_eq!(memory.set(1_000_000_000_000_000, 1), Err(MemoryError::AddressTooLarge { addr: 1_000_000_000_000_000 }));
+        assert_eq!(memory.set(MAX_LEN, 1), Err(MemoryError::AddressTooLarge { addr: MAX_LEN }));
+        assert!(memory.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use super::{IntcodeError, State, Status};
+
+/// The address the NAT listens on.
+pub const NAT: usize = 255;
+
+/// Rounds in a row in which nothing happens before the network is taken to be idle: a NIC that
+/// was just handed -1 may still be busy working out what to send.
+pub const IDLE_ROUNDS: usize = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Packet {
+    pub from: usize,
+    pub to: usize,
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NetworkEvent {
+    /// A NIC sent a packet, possibly to the NAT or to an address nobody listens on.
+    Sent(Packet),
+    /// The network went idle and the NAT sent the last packet it received to address 0.
+    Idle(Packet),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NetworkError {
+    Intcode(IntcodeError),
+    /// A NIC sent a packet to a negative address.
+    InvalidAddress { from: usize, address: i64 },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetworkError::Intcode(ref error) => write!(f, "{}", error),
+            NetworkError::InvalidAddress { from, address } => write!(f, "NIC {} sent a packet to invalid address {}", from, address),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+impl From<IntcodeError> for NetworkError {
+    fn from(error: IntcodeError) -> Self {
+        NetworkError::Intcode(error)
+    }
+}
+
+/// Packet-switched network of NICs, each one booted with its address and exchanging packets as
+/// (address, x, y) triples of outputs. The router runs the NICs one after the other: every round,
+/// each NIC receives the next packet of its queue, or -1 if there's none, then runs until it asks
+/// for input again. After `IDLE_ROUNDS` rounds in a row in which no NIC received or sent anything,
+/// or was halfway through sending a packet, the network is idle, which wakes up the NAT. Since
+/// nothing depends on timing, runs are reproducible.
+#[derive(Debug)]
+pub struct Network {
+    pub nics: Vec<State>,
+    queues: Vec<VecDeque<(i64, i64)>>,
+    /// Outputs of each NIC that don't make a whole packet yet.
+    pending: Vec<Vec<i64>>,
+    halted: Vec<bool>,
+    /// Rounds in a row in which nothing happened.
+    idle_rounds: usize,
+    /// The last packet sent to the NAT.
+    pub nat: Option<(i64, i64)>,
+}
+
+impl Network {
+    /// Boots `size` NICs running `program`.
+    pub fn new(program: &[i64], size: usize) -> Self {
+        let nics = (0..size).map(|address| {
+            let mut nic = State::new(program);
+            nic.id = address;
+            nic.push_input(address as i64);
+            nic
+        }).collect();
+        Network{nics, queues: vec![VecDeque::new(); size], pending: vec![Vec::new(); size], halted: vec![false; size], idle_rounds: 0, nat: None}
+    }
+
+    /// Routes a packet as if a NIC had sent it.
+    pub fn send(&mut self, packet: Packet) {
+        if packet.to == NAT {
+            self.nat = Some((packet.x, packet.y));
+        } else if let Some(queue) = self.queues.get_mut(packet.to) {
+            queue.push_back((packet.x, packet.y));
+        }
+    }
+
+    /// Runs every NIC once and returns what happened on the network, in order.
+    pub fn round(&mut self) -> Result<Vec<NetworkEvent>, NetworkError> {
+        let mut events = Vec::new();
+        let mut idle = true;
+
+        for address in 0..self.nics.len() {
+            if self.halted[address] {
+                continue
+            }
+
+            match self.queues[address].pop_front() {
+                Some((x, y)) => {
+                    idle = false;
+                    self.nics[address].push_input(x);
+                    self.nics[address].push_input(y);
+                },
+                None => self.nics[address].push_input(-1),
+            }
+
+            loop {
+                match self.nics[address].run()? {
+                    Status::Output(value) => {
+                        self.pending[address].push(value);
+                        if self.pending[address].len() == 3 {
+                            let to = usize::try_from(self.pending[address][0])
+                                .map_err(|_| NetworkError::InvalidAddress { from: address, address: self.pending[address][0] })?;
+                            let packet = Packet{from: address, to, x: self.pending[address][1], y: self.pending[address][2]};
+                            self.pending[address].clear();
+                            self.send(packet);
+                            events.push(NetworkEvent::Sent(packet));
+                            idle = false;
+                        }
+                    },
+                    Status::NeedsInput => {
+                        if !self.pending[address].is_empty() {
+                            idle = false;
+                        }
+                        break
+                    },
+                    Status::Halted => {
+                        self.halted[address] = true;
+                        break
+                    },
+                }
+            }
+        }
+
+        self.idle_rounds = if idle { self.idle_rounds + 1 } else { 0 };
+        if self.idle_rounds >= IDLE_ROUNDS {
+            if let Some((x, y)) = self.nat {
+                self.idle_rounds = 0;
+                let packet = Packet{from: NAT, to: 0, x, y};
+                self.send(packet);
+                events.push(NetworkEvent::Idle(packet));
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // passes every packet it receives on to the next address with y incremented, the last NIC
+    // sending it to the NAT
+    const RELAY: &str = "
+        size = 3
+                in addr
+        loop:   in x
+                eq x, #-1, tmp
+                jnz tmp, #loop
+                in y
+                add addr, #1, dest
+                lt dest, #size, tmp
+                jnz tmp, #send
+                add #255, #0, dest
+        send:   out dest
+                out x
+                add y, #1, y
+                out y
+                jz #0, #loop
+        addr:   db 0
+        x:      db 0
+        y:      db 0
+        dest:   db 0
+        tmp:    db 0
+    ";
+
+    fn sent(from: usize, to: usize, x: i64, y: i64) -> NetworkEvent {
+        NetworkEvent::Sent(Packet{from, to, x, y})
+    }
+
+    #[test]
+    fn test_relay() {
+        let mut network = Network::new(&assemble(RELAY).unwrap(), 3);
+        assert_eq!(network.round(), Ok(vec![]));
+
+        network.send(Packet{from: NAT, to: 0, x: 7, y: 0});
+        assert_eq!(network.round(), Ok(vec![sent(0, 1, 7, 1), sent(1, 2, 7, 2), sent(2, NAT, 7, 3)]));
+        assert_eq!(network.nat, Some((7, 3)));
+        assert_eq!(network.round(), Ok(vec![]));
+        assert_eq!(network.round(), Ok(vec![NetworkEvent::Idle(Packet{from: NAT, to: 0, x: 7, y: 3})]));
+        assert_eq!(network.round(), Ok(vec![sent(0, 1, 7, 4), sent(1, 2, 7, 5), sent(2, NAT, 7, 6)]));
+    }
+
+    #[test]
+    fn test_unknown_address() {
+        // sends a single packet to address 9, then listens forever
+        let mut network = Network::new(&[3,100,104,9,104,1,104,2,3,100,1105,1,8], 2);
+        assert_eq!(network.round(), Ok(vec![sent(0, 9, 1, 2), sent(1, 9, 1, 2)]));
+        assert_eq!(network.round(), Ok(vec![]));
+        assert_eq!(network.nat, None);
+    }
+
+    #[test]
+    fn test_slow_nic() {
+        // NIC 0 only sends its packet to the NAT on the second -1 it's handed, which must not be
+        // taken for the network going idle
+        let program = assemble("
+                    in addr
+            wait:   in x
+                    add count, #1, count
+                    lt count, #2, tmp
+                    jnz tmp, #wait
+                    jnz addr, #wait
+                    out #255
+                    out #1
+                    out #2
+            idle:   in x
+                    jz #0, #idle
+            addr:   db 0
+            x:      db 0
+            count:  db 0
+            tmp:    db 0
+        ").unwrap();
+        let mut network = Network::new(&program, 2);
+        network.send(Packet{from: 1, to: NAT, x: 5, y: 6});
+        assert_eq!(network.round(), Ok(vec![]));
+        assert_eq!(network.round(), Ok(vec![sent(0, NAT, 1, 2)]));
+        assert_eq!(network.round(), Ok(vec![]));
+        assert_eq!(network.round(), Ok(vec![NetworkEvent::Idle(Packet{from: NAT, to: 0, x: 1, y: 2})]));
+    }
+
+    #[test]
+    fn test_negative_address() {
+        let mut network = Network::new(&[3,100,104,-1,104,1,104,2,99], 1);
+        assert_eq!(network.round(), Err(NetworkError::InvalidAddress { from: 0, address: -1 }));
+    }
+}
@@ -0,0 +1,329 @@
+//! Patches overwrite cells of a program before it runs, one `address = value` per line:
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! noun: 1             ; labels name addresses
+//! verb: noun+1
+//! noun = 12
+//! verb = 2
+//! 0 = 2 if 0 == 1     ; only applied if the cell holds 1 at that point
+//! noun = 0..=99       ; every value of a range is tried by a sweep
+//! ```
+//!
+//! Addresses are expressions like the assembler's operands, so the `0012` addresses of a listing
+//! can be used as they are. Patches are applied in order, conditions being checked against the
+//! program as patched so far. Outside of a sweep, a range pokes its first value.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::asm::{evaluate, is_identifier, strip_comment};
+use super::{AsmError, Memory, MemoryError, MAX_LEN};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PatchError {
+    Syntax { line: usize, text: String },
+    UndefinedLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+    InvalidAddress { line: usize, address: i64 },
+    EmptyRange { line: usize },
+}
+
+impl PatchError {
+    /// The 1-based line the error was found on.
+    pub fn line(&self) -> usize {
+        match *self {
+            PatchError::Syntax { line, .. } |
+            PatchError::UndefinedLabel { line, .. } |
+            PatchError::DuplicateLabel { line, .. } |
+            PatchError::InvalidAddress { line, .. } |
+            PatchError::EmptyRange { line } => line,
+        }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatchError::Syntax { line, ref text } =>
+                write!(f, "line {}: can't make sense of {}", line, text),
+            PatchError::UndefinedLabel { line, ref label } =>
+                write!(f, "line {}: undefined label {}", line, label),
+            PatchError::DuplicateLabel { line, ref label } =>
+                write!(f, "line {}: {} is already defined", line, label),
+            PatchError::InvalidAddress { line, address } =>
+                write!(f, "line {}: invalid address {}", line, address),
+            PatchError::EmptyRange { line } =>
+                write!(f, "line {}: the range is empty", line),
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Values {
+    One(i64),
+    /// Every value from the first to the last, included.
+    Range(i64, i64),
+}
+
+impl Values {
+    fn first(self) -> i64 {
+        match self {
+            Values::One(value) | Values::Range(value, _) => value,
+        }
+    }
+}
+
+impl fmt::Display for Values {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Values::One(value) => write!(f, "{}", value),
+            Values::Range(first, last) => write!(f, "{}..={}", first, last),
+        }
+    }
+}
+
+/// Only apply a poke if the cell at `addr` holds `value`, or doesn't if `equal` is false.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Condition {
+    pub addr: usize,
+    pub value: i64,
+    pub equal: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Poke {
+    pub addr: usize,
+    pub values: Values,
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Patch {
+    pub labels: BTreeMap<String, usize>,
+    pub pokes: Vec<Poke>,
+}
+
+impl Patch {
+    /// Applies the patch, returning how many pokes were applied, the others' conditions not
+    /// holding. The program grows if a poke is past its end, up to the same size as a machine's
+    /// memory.
+    pub fn apply(&self, program: &mut Vec<i64>) -> Result<usize, MemoryError> {
+        let values = self.pokes.iter().map(|poke| poke.values.first()).collect::<Vec<_>>();
+        self.apply_values(program, &values)
+    }
+
+    pub(super) fn apply_values(&self, program: &mut Vec<i64>, values: &[i64]) -> Result<usize, MemoryError> {
+        let mut applied = 0;
+        for (poke, &value) in self.pokes.iter().zip(values) {
+            if let Some(condition) = poke.condition {
+                if (program.get(condition.addr).cloned().unwrap_or(0) == condition.value) != condition.equal {
+                    continue
+                }
+            }
+
+            Memory::check(poke.addr)?;
+            if poke.addr >= program.len() {
+                program.resize(poke.addr + 1, 0);
+            }
+            program[poke.addr] = value;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// The patch with each poke's values narrowed down to one, such as a candidate a sweep found.
+    pub fn with_values(&self, values: &[i64]) -> Patch {
+        let mut patch = self.clone();
+        for (poke, &value) in patch.pokes.iter_mut().zip(values) {
+            poke.values = Values::One(value);
+        }
+        patch
+    }
+
+    /// The value poked at a label by the last poke there, unless it's a range.
+    pub fn value(&self, label: &str) -> Option<i64> {
+        let addr = *self.labels.get(label)?;
+        match self.pokes.iter().rev().find(|poke| poke.addr == addr)?.values {
+            Values::One(value) => Some(value),
+            Values::Range(..) => None,
+        }
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut symbols: HashMap<&str, i64> = HashMap::new();
+        let mut statements = Vec::new();
+
+        // first pass: labels, so that pokes can use the ones defined after them
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let rest = strip_comment(line).trim();
+            if rest.is_empty() {
+                continue
+            }
+
+            match (rest.find(':'), rest.find('=')) {
+                (Some(colon), None) if is_identifier(rest[..colon].trim()) => {
+                    let label = rest[..colon].trim();
+                    let addr = address(&rest[colon + 1..], &symbols, line_number)?;
+                    if symbols.insert(label, addr as i64).is_some() {
+                        return Err(PatchError::DuplicateLabel { line: line_number, label: label.to_string() })
+                    }
+                },
+                (None, Some(_)) => statements.push((line_number, rest)),
+                _ => return Err(PatchError::Syntax { line: line_number, text: rest.to_string() }),
+            }
+        }
+
+        let mut pokes = Vec::new();
+        for (line, rest) in statements {
+            let syntax = || PatchError::Syntax { line, text: rest.to_string() };
+            let (poke, condition) = match rest.find(" if ") {
+                Some(at) => (&rest[..at], Some(&rest[at + 4..])),
+                None => (rest, None),
+            };
+
+            let eq = poke.find('=').ok_or_else(syntax)?;
+            let addr = address(&poke[..eq], &symbols, line)?;
+            let values = poke[eq + 1..].trim();
+            let values = if let Some(dots) = values.find("..") {
+                let first = value(&values[..dots], &symbols, line)?;
+                let last = match values[dots + 2..].strip_prefix('=') {
+                    Some(last) => value(last, &symbols, line)?,
+                    None => value(&values[dots + 2..], &symbols, line)?.checked_sub(1).ok_or(PatchError::EmptyRange { line })?,
+                };
+                if last < first {
+                    return Err(PatchError::EmptyRange { line })
+                }
+                Values::Range(first, last)
+            } else {
+                Values::One(value(values, &symbols, line)?)
+            };
+
+            let condition = match condition {
+                Some(condition) => {
+                    let (at, equal) = match (condition.find("=="), condition.find("!=")) {
+                        (Some(at), None) => (at, true),
+                        (None, Some(at)) => (at, false),
+                        _ => return Err(syntax()),
+                    };
+                    Some(Condition{
+                        addr: address(&condition[..at], &symbols, line)?,
+                        value: value(&condition[at + 2..], &symbols, line)?,
+                        equal,
+                    })
+                },
+                None => None,
+            };
+
+            pokes.push(Poke{addr, values, condition});
+        }
+
+        let labels = symbols.into_iter().map(|(label, addr)| (label.to_string(), addr as usize)).collect();
+        Ok(Patch{labels, pokes})
+    }
+}
+
+fn value(expr: &str, symbols: &HashMap<&str, i64>, line: usize) -> Result<i64, PatchError> {
+    evaluate(expr, symbols, line).map_err(|error| match error {
+        AsmError::UndefinedSymbol { line, symbol } => PatchError::UndefinedLabel { line, label: symbol },
+        _ => PatchError::Syntax { line, text: expr.trim().to_string() },
+    })
+}
+
+fn address(expr: &str, symbols: &HashMap<&str, i64>, line: usize) -> Result<usize, PatchError> {
+    match value(expr, symbols, line)? {
+        address if address < 0 || address as u64 >= MAX_LEN as u64 => Err(PatchError::InvalidAddress { line, address }),
+        address => Ok(address as usize),
+    }
+}
+
+/// Writes the patch back in the format it's parsed from, naming addresses after their labels.
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = HashMap::new();
+        for (label, &addr) in &self.labels {
+            writeln!(f, "{}: {}", label, addr)?;
+            names.entry(addr).or_insert_with(|| label.clone());
+        }
+        let name = |addr: usize| names.get(&addr).cloned().unwrap_or_else(|| addr.to_string());
+
+        for poke in &self.pokes {
+            write!(f, "{} = {}", name(poke.addr), poke.values)?;
+            if let Some(condition) = poke.condition {
+                write!(f, " if {} {} {}", name(condition.addr), if condition.equal { "==" } else { "!=" }, condition.value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALARM: &str = "
+        ; restore the gravity assist program to its 1202 state
+        noun: 1
+        verb: noun+1
+        noun = 12
+        verb = 0002
+    ";
+
+    #[test]
+    fn test_apply() {
+        let patch = ALARM.parse::<Patch>().unwrap();
+        assert_eq!(patch.labels.get("verb"), Some(&2));
+        assert_eq!(patch.value("noun"), Some(12));
+
+        let mut program = vec![1, 0, 0, 0, 99];
+        assert_eq!(patch.apply(&mut program), Ok(2));
+        assert_eq!(program, vec![1, 12, 2, 0, 99]);
+
+        let mut program = vec![1, 0, 0, 0, 99];
+        let patch = "0 = 2 if 0 == 1\n0 = 3 if 0 == 1\n6 = 7 if 4 != 0".parse::<Patch>().unwrap();
+        assert_eq!(patch.apply(&mut program), Ok(2));
+        assert_eq!(program, vec![2, 0, 0, 0, 99, 0, 7]);
+
+        let mut patch = Patch::default();
+        patch.pokes.push(Poke{addr: 1_000_000_000_000_000, values: Values::One(1), condition: None});
+        assert_eq!(patch.apply(&mut program), Err(MemoryError::AddressTooLarge { addr: 1_000_000_000_000_000 }));
+    }
+
+    #[test]
+    fn test_display() {
+        let patch = "a: 3\nb: 5\na = -1..=4 if b != 2\n7 = 1..3".parse::<Patch>().unwrap();
+        assert_eq!(patch.to_string(), "a: 3\nb: 5\na = -1..=4 if b != 2\n7 = 1..=2\n");
+        assert_eq!(patch.to_string().parse::<Patch>(), Ok(patch));
+    }
+
+    #[test]
+    fn test_with_values() {
+        let patch = "noun: 1\nverb: 2\nnoun = 5..=8\nverb = 5..=8".parse::<Patch>().unwrap();
+        let found = patch.with_values(&[7, 8]);
+        assert_eq!((found.value("noun"), found.value("verb")), (Some(7), Some(8)));
+        assert_eq!(found.to_string(), "noun: 1\nverb: 2\nnoun = 7\nverb = 8\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!("a = 1".parse::<Patch>(), Err(PatchError::UndefinedLabel { line: 1, label: "a".to_string() }));
+        assert_eq!("a: 1\n\na: 2".parse::<Patch>(), Err(PatchError::DuplicateLabel { line: 3, label: "a".to_string() }));
+        assert_eq!("1 - 2 = 0".parse::<Patch>(), Err(PatchError::InvalidAddress { line: 1, address: -1 }));
+        assert_eq!("1000000000000000 = 0".parse::<Patch>(), Err(PatchError::InvalidAddress { line: 1, address: 1_000_000_000_000_000 }));
+        assert_eq!("0 = 3..3".parse::<Patch>(), Err(PatchError::EmptyRange { line: 1 }));
+        // nothing comes before the smallest value
+        assert_eq!("0 = 0..-9223372036854775807-1".parse::<Patch>(), Err(PatchError::EmptyRange { line: 1 }));
+        assert_eq!("0 = 1 if 1 < 2".parse::<Patch>(), Err(PatchError::Syntax { line: 1, text: "0 = 1 if 1 < 2".to_string() }));
+        assert_eq!("poke 1".parse::<Patch>(), Err(PatchError::Syntax { line: 1, text: "poke 1".to_string() }));
+    }
+}
@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{IntcodeError, State, Status};
+
+/// How a scheduler run ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Outcome {
+    /// Every machine halted.
+    Halted,
+    /// The machines that are still running are all waiting on inputs nobody is going to send.
+    Deadlock(Vec<usize>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SchedulerError {
+    /// No machine was added with this id.
+    UnknownMachine { id: usize },
+    /// A machine stopped on an error.
+    Machine { id: usize, error: IntcodeError },
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchedulerError::UnknownMachine { id } => write!(f, "no machine {}", id),
+            SchedulerError::Machine { id, ref error } => write!(f, "machine {}: {}", id, error),
+        }
+    }
+}
+
+impl Error for SchedulerError {}
+
+/// Handle to a connection between two machines of a scheduler.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Edge(pub(super) usize);
+
+#[derive(Debug)]
+struct Link {
+    from: usize,
+    to: usize,
+    /// Every value sent over the link, once it's observed.
+    traffic: Option<Vec<i64>>,
+}
+
+/// Runs synchronous machines cooperatively on the current thread. Each machine runs until it
+/// needs an input it doesn't have or halts, then the next one gets its turn. Outputs are pushed
+/// to the inputs of the machines connected to the one producing them.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    pub machines: Vec<State>,
+    links: Vec<Link>,
+    /// The links each machine's outputs go out on.
+    routes: Vec<Vec<usize>>,
+    halted: Vec<bool>,
+    /// Values output by machines that aren't connected to any other, along with their id.
+    pub outputs: Vec<(usize, i64)>,
+    /// The last value each machine output, whether it was routed or not.
+    pub last_outputs: Vec<Option<i64>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Adds a machine, returning its id, which is also stored in the machine.
+    pub fn add(&mut self, mut state: State) -> usize {
+        let id = self.machines.len();
+        state.id = id;
+        self.machines.push(state);
+        self.routes.push(Vec::new());
+        self.halted.push(false);
+        self.last_outputs.push(None);
+        id
+    }
+
+    /// Sends every output of `from` to `to`'s inputs from now on.
+    pub fn connect(&mut self, from: usize, to: usize) -> Result<Edge, SchedulerError> {
+        for &id in &[from, to] {
+            if id >= self.machines.len() {
+                return Err(SchedulerError::UnknownMachine { id })
+            }
+        }
+        self.links.push(Link{from, to, traffic: None});
+        self.routes[from].push(self.links.len() - 1);
+        Ok(Edge(self.links.len() - 1))
+    }
+
+    /// Looks up the edge going from `from` to `to`, if they're connected.
+    pub fn edge(&self, from: usize, to: usize) -> Option<Edge> {
+        self.links.iter().position(|link| link.from == from && link.to == to).map(Edge)
+    }
+
+    /// Sends `value` over `edge` as if its source machine had output it.
+    pub fn inject(&mut self, edge: Edge, value: i64) {
+        let link = &mut self.links[edge.0];
+        if let Some(ref mut traffic) = link.traffic {
+            traffic.push(value);
+        }
+        self.machines[link.to].push_input(value);
+    }
+
+    /// Starts recording the values sent over `edge`.
+    pub fn observe(&mut self, edge: Edge) {
+        let link = &mut self.links[edge.0];
+        if link.traffic.is_none() {
+            link.traffic = Some(Vec::new());
+        }
+    }
+
+    /// The values sent over `edge` since it's been observed.
+    pub fn traffic(&self, edge: Edge) -> &[i64] {
+        self.links[edge.0].traffic.as_ref().map(|traffic| &traffic[..]).unwrap_or(&[])
+    }
+
+    pub fn push_input(&mut self, machine: usize, value: i64) {
+        self.machines[machine].push_input(value);
+    }
+
+    pub fn is_halted(&self, machine: usize) -> bool {
+        self.halted[machine]
+    }
+
+    /// Runs the machines in turn until they all halt or none of them can make progress. New
+    /// inputs can be pushed after a deadlock and `run` called again. An error stops the run and is
+    /// returned along with the id of the machine that ran into it.
+    pub fn run(&mut self) -> Result<Outcome, SchedulerError> {
+        loop {
+            let mut progress = false;
+
+            for id in 0..self.machines.len() {
+                if self.halted[id] {
+                    continue
+                }
+                let pc = self.machines[id].pc;
+                let pending = self.machines[id].inputs.len();
+
+                loop {
+                    let status = self.machines[id].run().map_err(|error| SchedulerError::Machine { id, error })?;
+                    match status {
+                        Status::Output(value) => {
+                            self.last_outputs[id] = Some(value);
+                            if self.routes[id].is_empty() {
+                                self.outputs.push((id, value));
+                            }
+                            for i in 0..self.routes[id].len() {
+                                self.inject(Edge(self.routes[id][i]), value);
+                            }
+                            progress = true;
+                        },
+                        Status::NeedsInput => break,
+                        Status::Halted => {
+                            self.halted[id] = true;
+                            progress = true;
+                            break
+                        },
+                    }
+                }
+
+                // a machine blocked on input right where it was didn't get anywhere
+                progress |= self.machines[id].pc != pc || self.machines[id].inputs.len() != pending;
+            }
+
+            if self.halted.iter().all(|halted| *halted) {
+                return Ok(Outcome::Halted)
+            }
+            if !progress {
+                return Ok(Outcome::Deadlock((0..self.machines.len()).filter(|id| !self.halted[*id]).collect()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // adds up every input and outputs the running total
+    const ACCUMULATOR: &[i64] = &[3,12,1,12,13,13,4,13,1105,1,0,99,0,0];
+
+    #[test]
+    fn test_ring() {
+        // a doubler and an incrementer feeding each other, the latter halting once past 100
+        let double = [3,13,1002,13,2,13,4,13,1105,1,0,99,0,0];
+        let increment_until_100 = [3,17,1001,17,1,17,4,17,7,17,18,16,1005,16,0,99,0,0,100];
+        let mut scheduler = Scheduler::new();
+        let a = scheduler.add(State::new(&double));
+        let b = scheduler.add(State::new(&increment_until_100));
+        scheduler.connect(a, b).unwrap();
+        scheduler.connect(b, a).unwrap();
+        scheduler.push_input(a, 1);
+
+        assert_eq!(scheduler.run(), Ok(Outcome::Deadlock(vec![a])));
+        assert!(scheduler.is_halted(b));
+        assert_eq!(scheduler.last_outputs, vec![Some(254), Some(127)]);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut scheduler = Scheduler::new();
+        let a = scheduler.add(State::new(ACCUMULATOR));
+        let b = scheduler.add(State::new(ACCUMULATOR));
+        scheduler.connect(a, b).unwrap();
+        assert_eq!(scheduler.run(), Ok(Outcome::Deadlock(vec![a, b])));
+
+        scheduler.push_input(a, 2);
+        scheduler.push_input(a, 3);
+        assert_eq!(scheduler.run(), Ok(Outcome::Deadlock(vec![a, b])));
+        assert_eq!(scheduler.outputs, vec![(b, 2), (b, 7)]);
+        assert_eq!(scheduler.last_outputs, vec![Some(5), Some(7)]);
+    }
+
+    #[test]
+    fn test_halted() {
+        let mut scheduler = Scheduler::new();
+        let a = scheduler.add(State::new(&[104,7,104,8,99]));
+        let b = scheduler.add(State::new(&[3,11,3,12,1,11,12,13,4,13,99]));
+        scheduler.connect(a, b).unwrap();
+
+        assert_eq!(scheduler.run(), Ok(Outcome::Halted));
+        assert_eq!(scheduler.outputs, vec![(b, 15)]);
+        assert_eq!(scheduler.machines[b].id, b);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut scheduler = Scheduler::new();
+        let a = scheduler.add(State::new(ACCUMULATOR));
+        let b = scheduler.add(State::new(&[3,5,3,5,42,0]));
+        assert_eq!(scheduler.connect(a, 2), Err(SchedulerError::UnknownMachine { id: 2 }));
+        scheduler.connect(a, b).unwrap();
+
+        scheduler.push_input(a, 1);
+        scheduler.push_input(a, 2);
+        assert_eq!(scheduler.run(), Err(SchedulerError::Machine { id: b, error: IntcodeError::UnknownOpcode { pc: 4, instruction: 42 } }));
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut scheduler = Scheduler::new();
+        let a = scheduler.add(State::new(ACCUMULATOR));
+        let b = scheduler.add(State::new(ACCUMULATOR));
+        let c = scheduler.add(State::new(ACCUMULATOR));
+        let ab = scheduler.connect(a, b).unwrap();
+        let bc = scheduler.connect(b, c).unwrap();
+        scheduler.connect(a, c).unwrap();
+        assert_eq!(scheduler.edge(b, c), Some(bc));
+        assert_eq!(scheduler.edge(c, a), None);
+
+        scheduler.observe(ab);
+        scheduler.observe(bc);
+        scheduler.push_input(a, 1);
+        scheduler.push_input(a, 2);
+        scheduler.run().unwrap();
+        scheduler.inject(ab, 10);
+        scheduler.run().unwrap();
+
+        assert_eq!(scheduler.traffic(ab), &[1, 3, 10]);
+        assert_eq!(scheduler.traffic(bc), &[1, 4, 14]);
+        assert_eq!(scheduler.outputs, vec![(c, 1), (c, 4), (c, 5), (c, 9), (c, 23)]);
+    }
+}
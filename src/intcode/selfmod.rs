@@ -0,0 +1,171 @@
+//! Opt-in detection of self-modifying code. Once a `SelfMod` is attached to `State::selfmod`,
+//! every cell an executed instruction spans is marked as code, and writes landing on code are
+//! flagged: either on an instruction that already ran, or on the one about to run next, as in
+//! day 5's `1002,4,3,4,33` which turns its last cell into a `halt` just before reaching it.
+//!
+//! Static disassembly can't know about either, so the report is a list of the places where a
+//! listing of the program as loaded is wrong about what actually runs.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::disasm::{decode, Opcode};
+use super::Memory;
+
+/// What a flagged write landed on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Target {
+    /// A cell of an instruction that was already executed.
+    Executed,
+    /// A cell of the instruction right after the one writing.
+    Next,
+}
+
+/// Writes by the instruction at `pc` to the cell at `addr`, flagged at least once.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CodeWrite {
+    pub pc: usize,
+    pub addr: usize,
+    pub target: Target,
+    /// The cell's value before the first flagged write, and after the last one.
+    pub first: i64,
+    pub last: i64,
+    pub count: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SelfMod {
+    /// Cells spanned by an instruction that was executed.
+    pub executed: BTreeSet<usize>,
+    /// Cells written to by the program.
+    pub written: BTreeSet<usize>,
+    /// Flagged writes, by writing instruction and written cell.
+    pub writes: BTreeMap<(usize, usize), CodeWrite>,
+}
+
+impl SelfMod {
+    pub fn new() -> Self {
+        SelfMod::default()
+    }
+
+    /// Marks the cells of the instruction `op` at `pc` as code, about to be executed.
+    pub(super) fn executing(&mut self, pc: usize, op: i64) {
+        let arity = Opcode::from_code(op % 100).map(|opcode| opcode.arity()).unwrap_or(0);
+        self.executed.extend(pc..=pc + arity);
+    }
+
+    /// Records the write of `value` over `old` at `addr` by the instruction `op` at `pc`, once
+    /// it's been done to `memory`.
+    pub(super) fn wrote(&mut self, memory: &Memory, pc: usize, op: i64, addr: usize, old: i64, value: i64) {
+        self.written.insert(addr);
+
+        let target = if self.executed.contains(&addr) {
+            Target::Executed
+        } else {
+            // writing instructions never jump, the next one is right after
+            let next = pc + 1 + Opcode::from_code(op % 100).map(|opcode| opcode.arity()).unwrap_or(0);
+            let cells = (next..next + 4).map(|addr| memory.get(addr)).collect::<Vec<_>>();
+            let size = decode(&cells, 0).map(|instruction| instruction.size()).unwrap_or(1);
+            if (next..next + size).contains(&addr) {
+                Target::Next
+            } else {
+                return
+            }
+        };
+
+        let write = self.writes.entry((pc, addr)).or_insert(CodeWrite{pc, addr, target, first: old, last: value, count: 0});
+        write.target = write.target.min(target);
+        write.last = value;
+        write.count += 1;
+    }
+
+    /// Cells that were both executed and written, in whichever order.
+    pub fn modified(&self) -> Vec<usize> {
+        self.executed.intersection(&self.written).cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty() && self.modified().is_empty()
+    }
+}
+
+impl fmt::Display for CodeWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let target = match self.target {
+            Target::Executed => "executed",
+            Target::Next => "next",
+        };
+        write!(f, "{:04} -> {:04}  {:<8} {} -> {}", self.pc, self.addr, target, self.first, self.last)?;
+        if self.count > 1 {
+            write!(f, " ({} times)", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The flagged writes, one per line, then the cells that were both executed and written.
+impl fmt::Display for SelfMod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} writes to code:", self.writes.len())?;
+        for write in self.writes.values() {
+            writeln!(f, "  {}", write)?;
+        }
+        let modified = self.modified();
+        writeln!(f, "\n{} cells executed and written:", modified.len())?;
+        for line in modified.chunks(16) {
+            writeln!(f, "  {}", line.iter().map(|addr| format!("{:04}", addr)).collect::<Vec<_>>().join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{assemble, State, Status};
+
+    #[test]
+    fn test_next() {
+        let mut state = State::new(&[1002,4,3,4,33]);
+        state.selfmod = Some(SelfMod::new());
+        assert_eq!(state.run(), Ok(Status::Halted));
+
+        let selfmod = state.selfmod.unwrap();
+        assert_eq!(selfmod.writes.values().cloned().collect::<Vec<_>>(), vec![
+            CodeWrite{pc: 0, addr: 4, target: Target::Next, first: 33, last: 99, count: 1},
+        ]);
+        assert_eq!(selfmod.modified(), vec![4]);
+        assert_eq!(selfmod.to_string(), "1 writes to code:\n  0000 -> 0004  next     33 -> 99\n\n1 cells executed and written:\n  0004\n");
+    }
+
+    #[test]
+    fn test_executed() {
+        // counts up by patching the immediate operand of its own `add`, copying it into a data
+        // cell whose writes aren't flagged
+        let program = assemble("
+            loop:   add #0, #0, n
+                    add loop + 1, #1, loop + 1
+                    lt n, #2, tmp
+                    jnz tmp, #loop
+                    halt
+            n:      db 0
+            tmp:    db 0
+        ").unwrap();
+        let mut state = State::new(&program);
+        state.selfmod = Some(SelfMod::new());
+        assert_eq!(state.run(), Ok(Status::Halted));
+
+        let selfmod = state.selfmod.unwrap();
+        assert_eq!(selfmod.writes.values().cloned().collect::<Vec<_>>(), vec![
+            CodeWrite{pc: 4, addr: 1, target: Target::Executed, first: 0, last: 3, count: 3},
+        ]);
+        assert_eq!(selfmod.modified(), vec![1]);
+        assert!(!selfmod.is_empty());
+
+        // a program that only writes to data
+        let mut state = State::new(&[1,0,0,5,99,0]);
+        state.selfmod = Some(SelfMod::new());
+        state.run().unwrap();
+        assert!(state.selfmod.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::fields::Fields;
+use super::{Memory, State};
+
+const HEADER: &str = "intcode-snapshot";
+const VERSION: u32 = 1;
+
+/// Everything needed to bring a machine back to the point it was captured at: its memory,
+/// registers, the inputs it has been given but hasn't read yet and the outputs it buffered that
+/// haven't been taken.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    pub memory: Memory,
+    pub pc: i64,
+    pub relative_base: i64,
+    pub inputs: VecDeque<i64>,
+    pub outputs: VecDeque<i64>,
+}
+
+/// Snapshots are stored as text, one field per line after a version header:
+///
+/// ```text
+/// intcode-snapshot 1
+/// pc 4
+/// relative_base 0
+/// inputs 7,3
+/// outputs
+/// memory 3,12,1,12,13,13,4,13,1105,1,0,99,5,5
+/// ```
+impl Snapshot {
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        fn join<'a, I: IntoIterator<Item = &'a i64>>(values: I) -> String {
+            values.into_iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+        }
+
+        writeln!(out, "{} {}", HEADER, VERSION)?;
+        writeln!(out, "pc {}", self.pc)?;
+        writeln!(out, "relative_base {}", self.relative_base)?;
+        writeln!(out, "inputs {}", join(&self.inputs))?;
+        writeln!(out, "outputs {}", join(&self.outputs))?;
+        writeln!(out, "memory {}", join(self.memory.as_slice()))
+    }
+
+    pub fn read_from<R: BufRead>(input: R) -> io::Result<Snapshot> {
+        let mut fields = Fields::open(input, HEADER, VERSION, "snapshot")?;
+        let pc = fields.parse("pc")?;
+        let relative_base = fields.parse("relative_base")?;
+        let inputs = fields.list("inputs")?.into_iter().collect();
+        let outputs = fields.list("outputs")?.into_iter().collect();
+        let memory = Memory::from(&fields.list("memory")?[..]);
+
+        Ok(Snapshot{memory, pc, relative_base, inputs, outputs})
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        Snapshot::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+impl State {
+    /// Captures the machine. Values its source hasn't handed over yet aren't part of the
+    /// snapshot.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot{memory: self.memory.clone(), pc: self.pc, relative_base: self.relative_base, inputs: self.inputs.clone(), outputs: self.outputs.clone()}
+    }
+
+    /// Rewinds the machine to `snapshot`, keeping its id, source, sink, tracer and limits. The
+    /// step count starts over, so a step limit applies to each run from the snapshot. Memory is
+    /// copied into the machine's own, so restoring over and over reuses its allocation.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.steps = 0;
+        self.memory.clone_from(&snapshot.memory);
+        self.pc = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.inputs.clone_from(&snapshot.inputs);
+        self.outputs.clone_from(&snapshot.outputs);
+    }
+
+    /// Builds a synchronous machine resuming from `snapshot`.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        let mut state = State::new(&[]);
+        state.restore(snapshot);
+        state
+    }
+
+    /// Returns an independent synchronous copy of the machine, sharing nothing with it: running
+    /// either one leaves the other untouched. The fork keeps the machine's limits, and a copy of
+    /// what its self-modification tracking has seen so far, but isn't traced: a tracer's sink
+    /// can't be shared, and its counts would end up covering both machines.
+    pub fn fork(&self) -> Self {
+        let mut state = State::from_snapshot(&self.snapshot());
+        state.id = self.id;
+        state.steps = self.steps;
+        state.limits = self.limits;
+        state.selfmod = self.selfmod.clone();
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{IntcodeError, SelfMod, Status, Tracer};
+
+    // adds up every input and outputs the running total
+    const ACCUMULATOR: &[i64] = &[3,12,1,12,13,13,4,13,1105,1,0,99,0,0];
+
+    #[test]
+    fn test_fork() {
+        let mut state = State::new(ACCUMULATOR);
+        state.push_input(5);
+        assert_eq!(state.run(), Ok(Status::Output(5)));
+        state.push_input(1);
+
+        let mut fork = state.fork();
+        fork.push_input(100);
+        assert_eq!(fork.run(), Ok(Status::Output(6)));
+        assert_eq!(fork.run(), Ok(Status::Output(106)));
+
+        assert_eq!(state.run(), Ok(Status::Output(6)));
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+    }
+
+    #[test]
+    fn test_fork_keeps_limits() {
+        let mut state = State::new(ACCUMULATOR);
+        state.limits.steps = Some(6);
+        state.tracer = Some(Tracer::new());
+        state.selfmod = Some(SelfMod::new());
+        state.push_input(5);
+        assert_eq!(state.run(), Ok(Status::Output(5)));
+
+        let mut fork = state.fork();
+        assert!(fork.tracer.is_none());
+        assert_eq!(fork.selfmod.as_ref().map(|selfmod| selfmod.executed.len()), Some(8));
+        fork.push_input(1);
+        assert_eq!(fork.run(), Err(IntcodeError::StepLimit { pc: 6, instruction: 4, steps: 6 }));
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut state = State::new(ACCUMULATOR);
+        let start = state.snapshot();
+
+        state.push_input(5);
+        assert_eq!(state.run(), Ok(Status::Output(5)));
+        let checkpoint = state.snapshot();
+        state.push_input(1);
+        assert_eq!(state.run(), Ok(Status::Output(6)));
+
+        state.restore(&checkpoint);
+        state.push_input(2);
+        assert_eq!(state.run(), Ok(Status::Output(7)));
+
+        state.restore(&start);
+        assert_eq!(state.snapshot(), start);
+        assert_eq!(State::from_snapshot(&checkpoint).snapshot(), checkpoint);
+    }
+
+    #[test]
+    fn test_restore_resets_steps() {
+        let mut state = State::new(ACCUMULATOR);
+        state.limits.steps = Some(6);
+        state.push_input(5);
+        assert_eq!(state.run(), Ok(Status::Output(5)));
+        let checkpoint = state.snapshot();
+
+        for input in 1..4 {
+            state.restore(&checkpoint);
+            state.push_input(input);
+            assert_eq!(state.run(), Ok(Status::Output(5 + input)));
+            assert_eq!(state.steps, 4);
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut state = State::new(ACCUMULATOR);
+        state.push_input(5);
+        state.push_input(2);
+        state.push_input(1);
+        assert_eq!(state.run_to_input(), Ok(Status::NeedsInput));
+        state.push_input(7);
+        state.push_input(3);
+        let snapshot = state.snapshot();
+
+        let mut file = Vec::new();
+        snapshot.write_to(&mut file).unwrap();
+        assert_eq!(String::from_utf8(file.clone()).unwrap(), "intcode-snapshot 1\npc 0\nrelative_base 0\ninputs 7,3\noutputs 5,7,8\nmemory 3,12,1,12,13,13,4,13,1105,1,0,99,1,8\n");
+
+        let loaded = Snapshot::read_from(&file[..]).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let mut resumed = State::from_snapshot(&loaded);
+        assert_eq!(resumed.run_to_input(), Ok(Status::NeedsInput));
+        assert_eq!(resumed.outputs.drain(..).collect::<Vec<_>>(), vec![5, 7, 8, 15, 18]);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let load = |s: &str| Snapshot::read_from(s.as_bytes()).map_err(|e| e.to_string());
+        assert_eq!(load("intcode-snapshot 2\n"), Err("unsupported snapshot version: 2".to_string()));
+        assert_eq!(load("intcode-snapshot 1\npc 0\n"), Err("missing relative_base".to_string()));
+        assert_eq!(load("intcode-snapshot 1\npc x\n"), Err("invalid pc: x".to_string()));
+        assert_eq!(load("intcode-snapshot 1\npc 0\nrelative_base 0\noutputs\n"), Err("expected inputs, found: outputs".to_string()));
+        assert_eq!(load("intcode-snapshot 1\npc 0\nrelative_base 0\ninputs\noutputs\nmemory 1,,2,z"), Err("invalid memory: z".to_string()));
+    }
+}
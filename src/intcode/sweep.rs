@@ -0,0 +1,347 @@
+//! Brute-force searches over the inputs or the patched cells of a program, spread over a pool of
+//! threads. Candidates are numbered, which lets the workers share them out through a counter and
+//! keeps the results the same however many workers there are: `first` is the first candidate in
+//! order that matches, and `best` breaks ties in favour of the earlier candidate.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::{IntcodeError, Memory, MemoryError, Patch, State, Values};
+
+/// A space of candidates, each a list of values.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Space {
+    /// Every value of the range, as a candidate of its own.
+    Range(RangeInclusive<i64>),
+    /// Every ordering of the values, in lexicographic order of their positions.
+    Permutations(Vec<i64>),
+    /// Every way of picking one candidate from each space, put end to end, the last space's
+    /// changing the fastest.
+    Product(Vec<Space>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SweepError<E> {
+    /// The space has more candidates than can be numbered.
+    TooLarge,
+    /// Evaluating a candidate failed.
+    Candidate(E),
+}
+
+impl<E: fmt::Display> fmt::Display for SweepError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SweepError::TooLarge => write!(f, "too many candidates to search"),
+            SweepError::Candidate(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: Error> Error for SweepError<E> {}
+
+impl Space {
+    /// Number of candidates, `None` if there are too many to number them.
+    pub fn len(&self) -> Option<usize> {
+        match *self {
+            Space::Range(ref range) => if range.is_empty() { Some(0) } else { usize::try_from(*range.end() as i128 - *range.start() as i128 + 1).ok() },
+            Space::Permutations(ref values) => (1..=values.len()).try_fold(1usize, |count, n| count.checked_mul(n)),
+            Space::Product(ref spaces) => spaces.iter().try_fold(1usize, |count, space| count.checked_mul(space.len()?)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// The candidate numbered `index`.
+    pub fn get(&self, index: usize) -> Option<Vec<i64>> {
+        if index >= self.len()? {
+            return None
+        }
+        let mut candidate = Vec::new();
+        self.push(index, &mut candidate);
+        Some(candidate)
+    }
+
+    fn push(&self, mut index: usize, candidate: &mut Vec<i64>) {
+        match *self {
+            Space::Range(ref range) => candidate.push(range.start() + index as i64),
+            Space::Permutations(ref values) => {
+                let mut left = values.clone();
+                for remaining in (1..=values.len()).rev() {
+                    let block = (1..remaining).product::<usize>();
+                    candidate.push(left.remove(index / block));
+                    index %= block;
+                }
+            },
+            Space::Product(ref spaces) => {
+                let mut indices = vec![0; spaces.len()];
+                for (i, space) in spaces.iter().enumerate().rev() {
+                    // the whole space could be counted, so each of its parts can
+                    let len = space.len().unwrap_or(usize::MAX);
+                    indices[i] = index % len;
+                    index /= len;
+                }
+                for (space, index) in spaces.iter().zip(indices) {
+                    space.push(index, candidate);
+                }
+            },
+        }
+    }
+
+    /// The first candidate, in order, that passes `test`, evaluated on `workers` threads, or one
+    /// per core if 0. Candidates past one that passed aren't tried. An error stops the search
+    /// as well, and is returned if it came before any candidate that passed.
+    pub fn first<F, E>(&self, workers: usize, test: F) -> Result<Option<Vec<i64>>, SweepError<E>>
+        where F: Fn(&[i64]) -> Result<bool, E> + Sync, E: Send
+    {
+        let len = self.len().ok_or(SweepError::TooLarge)?;
+        let found = Mutex::new(None);
+        let result = pool(self, len, workers, |index, candidate, limit| {
+            if test(candidate)? {
+                limit.fetch_min(index, Ordering::SeqCst);
+                let mut found = found.lock().unwrap();
+                if found.as_ref().map(|&(first, _)| index < first).unwrap_or(true) {
+                    *found = Some((index, candidate.to_vec()));
+                }
+            }
+            Ok(())
+        });
+        // a slow match can come in after a later candidate failed: it's still the answer
+        match (result, found.into_inner().unwrap()) {
+            (Err((error_index, _)), Some((index, candidate))) if index < error_index => Ok(Some(candidate)),
+            (Err((_, error)), _) => Err(SweepError::Candidate(error)),
+            (Ok(()), found) => Ok(found.map(|(_, candidate)| candidate)),
+        }
+    }
+
+    /// The candidate that scores the highest, along with its score, evaluated on `workers`
+    /// threads, or one per core if 0. An error stops the search and is returned.
+    pub fn best<F, E>(&self, workers: usize, objective: F) -> Result<Option<(Vec<i64>, i64)>, SweepError<E>>
+        where F: Fn(&[i64]) -> Result<i64, E> + Sync, E: Send
+    {
+        let len = self.len().ok_or(SweepError::TooLarge)?;
+        let best = Mutex::new(None);
+        pool(self, len, workers, |index, candidate, _| {
+            let score = objective(candidate)?;
+            let mut best = best.lock().unwrap();
+            let better = match *best {
+                Some((best_score, best_index, _)) => score > best_score || (score == best_score && index < best_index),
+                None => true,
+            };
+            if better {
+                *best = Some((score, index, candidate.to_vec()));
+            }
+            Ok(())
+        }).map_err(|(_, error)| SweepError::Candidate(error))?;
+        Ok(best.into_inner().unwrap().map(|(score, _, candidate)| (candidate, score)))
+    }
+}
+
+/// Hands the first `len` candidates out to the workers in order, until they run out or reach
+/// the limit, which `evaluate` can lower to stop the search early. Every candidate before the
+/// final limit gets evaluated. On errors, the one of the earliest candidate is returned, along
+/// with its index.
+fn pool<F, E>(space: &Space, len: usize, workers: usize, evaluate: F) -> Result<(), (usize, E)>
+    where F: Fn(usize, &[i64], &AtomicUsize) -> Result<(), E> + Sync, E: Send
+{
+    let workers = match workers {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        workers => workers,
+    };
+    let next = AtomicUsize::new(0);
+    let limit = AtomicUsize::new(len);
+    let error = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let mut candidate = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= limit.load(Ordering::SeqCst) {
+                        break
+                    }
+
+                    candidate.clear();
+                    space.push(index, &mut candidate);
+                    if let Err(e) = evaluate(index, &candidate, &limit) {
+                        limit.fetch_min(index, Ordering::SeqCst);
+                        let mut error = error.lock().unwrap();
+                        if error.as_ref().map(|&(first, _)| index < first).unwrap_or(true) {
+                            *error = Some((index, e));
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    match error.into_inner().unwrap() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// A search over the runs of a program, every candidate getting a fresh machine: either queued
+/// as its inputs, or poked into its memory through a patch.
+#[derive(Debug)]
+pub struct Sweep<'a> {
+    program: &'a [i64],
+    space: Space,
+    patch: Option<Patch>,
+    workers: usize,
+}
+
+impl<'a> Sweep<'a> {
+    /// Candidates are queued as inputs.
+    pub fn inputs(program: &'a [i64], space: Space) -> Self {
+        Sweep{program, space, patch: None, workers: 0}
+    }
+
+    /// Candidates are values for the pokes of `patch`, a value from each poke's range. Fails if a
+    /// poke is past the end of memory.
+    pub fn patch(program: &'a [i64], patch: &Patch) -> Result<Self, MemoryError> {
+        for poke in &patch.pokes {
+            Memory::check(poke.addr)?;
+        }
+        let space = Space::Product(patch.pokes.iter().map(|poke| match poke.values {
+            Values::One(value) => Space::Range(value..=value),
+            Values::Range(first, last) => Space::Range(first..=last),
+        }).collect());
+        Ok(Sweep{program, space, patch: Some(patch.clone()), workers: 0})
+    }
+
+    /// Number of threads to run candidates on, one per core by default.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn space(&self) -> &Space {
+        &self.space
+    }
+
+    /// A fresh machine set up for `candidate`.
+    pub fn state(&self, candidate: &[i64]) -> State {
+        match self.patch {
+            Some(ref patch) => {
+                let mut program = self.program.to_vec();
+                // every poke was checked when the sweep was set up
+                patch.apply_values(&mut program, candidate).unwrap();
+                State::new(&program)
+            },
+            None => {
+                let mut state = State::new(self.program);
+                candidate.iter().for_each(|&value| state.push_input(value));
+                state
+            },
+        }
+    }
+
+    /// The first candidate whose machine passes `test`.
+    pub fn first<F>(&self, test: F) -> Result<Option<Vec<i64>>, SweepError<IntcodeError>>
+        where F: Fn(&mut State) -> Result<bool, IntcodeError> + Sync
+    {
+        self.space.first(self.workers, |candidate| test(&mut self.state(candidate)))
+    }
+
+    /// The candidate whose machine scores the highest, along with the score.
+    pub fn best<F>(&self, objective: F) -> Result<Option<(Vec<i64>, i64)>, SweepError<IntcodeError>>
+        where F: Fn(&mut State) -> Result<i64, IntcodeError> + Sync
+    {
+        self.space.best(self.workers, |candidate| objective(&mut self.state(candidate)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_space() {
+        let space = Space::Product(vec![Space::Range(1..=2), Space::Permutations(vec![7, 8, 9])]);
+        assert_eq!(space.len(), Some(12));
+        assert_eq!(space.get(0), Some(vec![1, 7, 8, 9]));
+        assert_eq!(space.get(1), Some(vec![1, 7, 9, 8]));
+        assert_eq!(space.get(5), Some(vec![1, 9, 8, 7]));
+        assert_eq!(space.get(6), Some(vec![2, 7, 8, 9]));
+        assert_eq!(space.get(12), None);
+        let (start, end) = (3, 2);
+        assert!(Space::Range(start..=end).is_empty());
+        assert_eq!(Space::Range(i64::MIN..=i64::MAX).len(), None);
+        assert_eq!(Space::Range(i64::MIN..=-1).len(), Some(1 << 63));
+        assert_eq!(Space::Permutations((0..20).collect()).len(), Some(2_432_902_008_176_640_000));
+        assert_eq!(Space::Permutations((0..21).collect()).len(), None);
+        let huge = Space::Product(vec![Space::Range(0..=1), Space::Permutations((0..20).collect()), Space::Range(0..=9)]);
+        assert_eq!(huge.len(), None);
+        assert_eq!(huge.get(0), None);
+        assert_eq!(huge.first(1, |_| Ok::<_, ()>(true)), Err(SweepError::TooLarge));
+
+        let permutations = (0..24).map(|i| Space::Permutations(vec![1, 2, 3, 4]).get(i).unwrap()).collect::<std::collections::HashSet<_>>();
+        assert_eq!(permutations.len(), 24);
+    }
+
+    #[test]
+    fn test_first_and_best() {
+        let space = Space::Product(vec![Space::Range(0..=9), Space::Range(0..=9)]);
+        for &workers in &[1, 3, 8] {
+            // 6 is reached by 0 + 6 first
+            assert_eq!(space.first(workers, |c| Ok::<_, ()>(c[0] + c[1] == 6)), Ok(Some(vec![0, 6])));
+            assert_eq!(space.first(workers, |c| Ok::<_, ()>(c[0] + c[1] == 20)), Ok(None));
+            // the product peaks at 9 * 9, sums of 9 tie and the first one is kept
+            assert_eq!(space.best(workers, |c| Ok::<_, ()>(c[0] * c[1])), Ok(Some((vec![9, 9], 81))));
+            assert_eq!(space.best(workers, |c| Ok::<_, ()>(-(c[0] + c[1] - 9).abs())), Ok(Some((vec![0, 9], 0))));
+            // an error before the first match wins, one after it doesn't matter
+            assert_eq!(space.first(workers, |c| if c[0] == 5 { Err(c[1]) } else { Ok(c[0] == 7) }), Err(SweepError::Candidate(0)));
+            assert_eq!(space.first(workers, |c| if c[0] == 5 { Err(c[1]) } else { Ok(c[0] == 3) }), Ok(Some(vec![3, 0])));
+        }
+    }
+
+    #[test]
+    fn test_first_slow_match_before_error() {
+        // 50 fails long before 10 is found to match
+        let space = Space::Range(0..=99);
+        let result = space.first(4, |c| match c[0] {
+            10 => { thread::sleep(Duration::from_millis(200)); Ok(true) },
+            50 => Err(50),
+            _ => Ok(false),
+        });
+        assert_eq!(result, Ok(Some(vec![10])));
+    }
+
+    #[test]
+    fn test_sweep() {
+        // outputs the product of its two inputs
+        let multiply = [3,11,3,12,2,11,12,13,4,13,99,0,0,0];
+        let sweep = Sweep::inputs(&multiply, Space::Permutations(vec![3, 4, 5])).workers(2);
+        let product = |state: &mut State| {
+            state.run_to_halt()?;
+            Ok(state.outputs[0])
+        };
+        assert_eq!(sweep.best(product), Ok(Some((vec![4, 5, 3], 20))));
+        assert_eq!(sweep.first(|state| Ok(product(state)? == 15)), Ok(Some(vec![3, 5, 4])));
+
+        // adds the cells at noun and verb into 0
+        let add = [1,0,0,0,99,10,20,30,40];
+        let patch = "noun: 1\nverb: 2\nnoun = 5..=8\nverb = 5..=8".parse::<Patch>().unwrap();
+        let sweep = Sweep::patch(&add, &patch).unwrap();
+        assert_eq!(sweep.space().len(), Some(16));
+        let sum = |state: &mut State| {
+            state.run_to_halt()?;
+            Ok(state.memory.get(0))
+        };
+        assert_eq!(sweep.first(|state| Ok(sum(state)? == 70)), Ok(Some(vec![7, 8])));
+        assert_eq!(sweep.best(sum), Ok(Some((vec![8, 8], 80))));
+
+        // running out of inputs
+        let sweep = Sweep::inputs(&multiply, Space::Range(1..=3));
+        assert_eq!(sweep.best(product), Err(SweepError::Candidate(IntcodeError::InputClosed { pc: 2, instruction: 3 })));
+    }
+}
@@ -0,0 +1,117 @@
+use super::scheduler::{Edge, Scheduler};
+use super::State;
+
+/// Declares a network of machines and how their outputs are wired to each other's inputs, to be
+/// run on a `Scheduler`. Outputs of machines without outgoing edges leave the network through the
+/// scheduler's `outputs`.
+#[derive(Debug, Default)]
+pub struct Topology {
+    nodes: Vec<State>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Topology::default()
+    }
+
+    /// One machine per phase, each one feeding the next.
+    pub fn pipeline(program: &[i64], phases: &[i64]) -> Self {
+        let mut topology = Topology::new();
+        for phase in phases {
+            let id = topology.node(State::new(program), &[*phase]);
+            if id > 0 {
+                topology.edge(id - 1, id);
+            }
+        }
+        topology
+    }
+
+    /// A pipeline whose last machine feeds back into the first one.
+    pub fn ring(program: &[i64], phases: &[i64]) -> Self {
+        let mut topology = Topology::pipeline(program, phases);
+        if !phases.is_empty() {
+            topology.edge(phases.len() - 1, 0);
+        }
+        topology
+    }
+
+    /// Adds a machine which reads `phase` before anything sent to it, returning its id.
+    pub fn node(&mut self, mut state: State, phase: &[i64]) -> usize {
+        phase.iter().rev().for_each(|value| state.inputs.push_front(*value));
+        self.nodes.push(state);
+        self.nodes.len() - 1
+    }
+
+    /// Routes every output of `from` to `to`. The returned handle refers to the same edge in the
+    /// built scheduler.
+    pub fn edge(&mut self, from: usize, to: usize) -> Edge {
+        assert!(from < self.nodes.len() && to < self.nodes.len(), "edge {} -> {} between unknown machines", from, to);
+        self.edges.push((from, to));
+        Edge(self.edges.len() - 1)
+    }
+
+    pub fn build(self) -> Scheduler {
+        let mut scheduler = Scheduler::new();
+        for state in self.nodes {
+            scheduler.add(state);
+        }
+        for (from, to) in self.edges {
+            // both ends were checked when the edge was added
+            scheduler.connect(from, to).unwrap();
+        }
+        scheduler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Outcome;
+
+    // reads a phase then a value, and outputs the value times ten plus the phase
+    const STAGE: &[i64] = &[3,15,3,16,1002,16,10,16,1,15,16,16,4,16,99];
+
+    #[test]
+    fn test_pipeline() {
+        let mut scheduler = Topology::pipeline(STAGE, &[1, 2, 3]).build();
+        let edge = scheduler.edge(1, 2).unwrap();
+        scheduler.observe(edge);
+        scheduler.push_input(0, 4);
+
+        assert_eq!(scheduler.run(), Ok(Outcome::Halted));
+        assert_eq!(scheduler.traffic(edge), &[412]);
+        assert_eq!(scheduler.outputs, vec![(2, 4123)]);
+    }
+
+    #[test]
+    fn test_graph() {
+        // a fans out to b and c, whose outputs both leave the network
+        let mut topology = Topology::new();
+        let a = topology.node(State::new(STAGE), &[1]);
+        let b = topology.node(State::new(STAGE), &[2]);
+        let c = topology.node(State::new(STAGE), &[3]);
+        let ab = topology.edge(a, b);
+        let ac = topology.edge(a, c);
+
+        let mut scheduler = topology.build();
+        assert_eq!(scheduler.edge(a, b), Some(ab));
+        assert_eq!(scheduler.edge(a, c), Some(ac));
+        scheduler.push_input(a, 5);
+        assert_eq!(scheduler.run(), Ok(Outcome::Halted));
+        assert_eq!(scheduler.outputs, vec![(b, 512), (c, 513)]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let mut scheduler = Topology::ring(STAGE, &[1, 2]).build();
+        let back = scheduler.edge(1, 0).unwrap();
+        scheduler.observe(back);
+        scheduler.push_input(0, 4);
+
+        // the value makes it back to the first machine after it has halted
+        assert_eq!(scheduler.run(), Ok(Outcome::Halted));
+        assert_eq!(scheduler.traffic(back), &[412]);
+        assert!(scheduler.outputs.is_empty());
+    }
+}
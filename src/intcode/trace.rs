@@ -0,0 +1,242 @@
+//! Opt-in execution tracing. Once a `Tracer` is attached to `State::tracer`, every executed
+//! instruction is counted per address and per opcode, and can be streamed to a writer as one
+//! compact line:
+//!
+//! ```text
+//! 75 3 384 [384]=0
+//! ^  ^ ^   ^ the cell written and its new value
+//! |  | +-- resolved operands: values for the read parameters, the address for the written one
+//! |  +---- raw instruction
+//! +------- pc
+//! ```
+//!
+//! The format is stable so that traces of two versions of the VM can be diffed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use super::disasm::{decode, Instruction, Opcode};
+use super::{addr_from_param, execute, value_from_param, IntcodeError, Memory, Next, State};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceEntry {
+    pub pc: i64,
+    pub instruction: i64,
+    pub operands: Vec<i64>,
+    pub write: Option<(usize, i64)>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.pc, self.instruction)?;
+        for operand in self.operands.iter() {
+            write!(f, " {}", operand)?;
+        }
+        if let Some((addr, value)) = self.write {
+            write!(f, " [{}]={}", addr, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Tracer {
+    sink: Option<Box<dyn Write + Send>>,
+    /// First error raised by the sink; tracing to it stops from there on.
+    pub error: Option<io::Error>,
+    pub steps: u64,
+    pub address_counts: BTreeMap<usize, u64>,
+    pub opcode_counts: BTreeMap<i64, u64>,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("steps", &self.steps)
+            .field("streaming", &self.sink.is_some())
+            .finish()
+    }
+}
+
+impl Tracer {
+    /// A tracer that only keeps the execution counts.
+    pub fn new() -> Self {
+        Tracer::default()
+    }
+
+    /// A tracer that also writes every executed instruction to `sink`.
+    pub fn to_writer<W: Write + Send + 'static>(sink: W) -> Self {
+        Tracer{sink: Some(Box::new(sink)), ..Tracer::default()}
+    }
+
+    pub fn record(&mut self, entry: &TraceEntry) {
+        self.steps += 1;
+        *self.address_counts.entry(entry.pc as usize).or_insert(0) += 1;
+        *self.opcode_counts.entry(entry.instruction % 100).or_insert(0) += 1;
+
+        if let Some(ref mut sink) = self.sink {
+            if let Err(e) = writeln!(sink, "{}", entry) {
+                self.error = Some(e);
+                self.sink = None;
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.sink {
+            Some(ref mut sink) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Renders the `top` most executed addresses along with their disassembly, and the number of
+    /// times each opcode was executed.
+    pub fn summary(&self, memory: &Memory, top: usize) -> String {
+        let mut out = format!("{} instructions executed\n\nhot spots:\n", self.steps);
+
+        let mut addresses = self.address_counts.iter().collect::<Vec<_>>();
+        addresses.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (addr, count) in addresses.into_iter().take(top) {
+            let cells = (*addr..addr + 4).map(|addr| memory.get(addr)).collect::<Vec<_>>();
+            let instruction = match decode(&cells, 0) {
+                Some(Instruction::Op { opcode, params, .. }) => Instruction::Op { addr: *addr, opcode, params },
+                _ => Instruction::Data { addr: *addr, value: cells[0] },
+            };
+            out.push_str(&format!("{:>12} {:>6.2}%  {:04}: {}\n", count, 100.0 * *count as f64 / self.steps as f64, addr, instruction));
+        }
+
+        out.push_str("\nopcodes:\n");
+        let mut opcodes = self.opcode_counts.iter().collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (code, count) in opcodes {
+            let name = Opcode::from_code(*code).map(|opcode| opcode.mnemonic()).unwrap_or("?");
+            out.push_str(&format!("{:>12} {:>6.2}%  {}\n", count, 100.0 * *count as f64 / self.steps as f64, name));
+        }
+
+        out
+    }
+}
+
+/// Executes the next instruction like `exec_op`, recording it with the state's tracer.
+pub(super) fn traced(state: &mut State) -> Result<Next, IntcodeError> {
+    let pc = state.pc;
+    let instruction = if pc >= 0 { state.memory.get(pc as usize) } else { 0 };
+
+    let mut operands = Vec::new();
+    let mut target = None;
+    if let Some(opcode) = Opcode::from_code(instruction % 100) {
+        for i in 0..opcode.arity() {
+            let param = i as i64 + 1;
+            let operand = if opcode.written_param() == Some(i) {
+                let addr = addr_from_param(state, instruction, param);
+                target = addr.ok();
+                addr
+            } else {
+                value_from_param(state, instruction, param)
+            };
+            // a bad operand will be reported by `execute`
+            operands.extend(operand.ok());
+        }
+    }
+
+    let next = execute(state)?;
+    // waiting on input, the instruction runs again once there is some
+    if next == Next::NeedsInput {
+        return Ok(next)
+    }
+
+    let write = match next {
+        Next::Continue => target.map(|addr| (addr as usize, state.memory.get(addr as usize))),
+        _ => None,
+    };
+
+    if let Some(ref mut tracer) = state.tracer {
+        tracer.record(&TraceEntry{pc, instruction, operands, write});
+    }
+
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{Status};
+
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let sink = Shared::default();
+        let mut state = State::new(&[3,15,1006,15,14,1002,15,2,15,4,15,1105,1,0,99,0][..]);
+        state.tracer = Some(Tracer::to_writer(sink.clone()));
+
+        state.push_input(3);
+        state.push_input(0);
+        assert_eq!(state.run(), Ok(Status::Output(6)));
+        assert_eq!(state.run(), Ok(Status::Halted));
+
+        assert_eq!(String::from_utf8(sink.0.lock().unwrap().clone()).unwrap(), "\
+0 3 15 [15]=3
+2 1006 3 14
+5 1002 3 2 15 [15]=6
+9 4 6
+11 1105 1 0
+0 3 15 [15]=0
+2 1006 0 14
+14 99
+");
+
+        let tracer = state.tracer.as_ref().unwrap();
+        assert_eq!(tracer.steps, 8);
+        assert_eq!(tracer.address_counts.get(&0), Some(&2));
+        assert_eq!(tracer.opcode_counts.get(&99), Some(&1));
+        assert_eq!(tracer.summary(&state.memory, 2), "\
+8 instructions executed
+
+hot spots:
+           2  25.00%  0000: in 15
+           2  25.00%  0002: jz 15, #14
+
+opcodes:
+           2  25.00%  in
+           2  25.00%  jz
+           1  12.50%  mul
+           1  12.50%  out
+           1  12.50%  jnz
+           1  12.50%  halt
+");
+    }
+
+    #[test]
+    fn test_waiting_on_input() {
+        let sink = Shared::default();
+        let mut state = State::new(&[3,9,4,9,99,0,0,0,0,0][..]);
+        state.tracer = Some(Tracer::to_writer(sink.clone()));
+
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+        assert_eq!(state.run(), Ok(Status::NeedsInput));
+        state.push_input(7);
+        assert_eq!(state.run(), Ok(Status::Output(7)));
+        assert_eq!(state.run(), Ok(Status::Halted));
+
+        assert_eq!(String::from_utf8(sink.0.lock().unwrap().clone()).unwrap(), "0 3 9 [9]=7\n2 4 7\n4 99\n");
+        let tracer = state.tracer.as_ref().unwrap();
+        assert_eq!(tracer.steps, 3);
+        assert_eq!(tracer.steps as usize, state.steps);
+        assert_eq!(tracer.address_counts.get(&0), Some(&1));
+    }
+}
@@ -1 +1,5 @@
+pub mod adventure;
+pub mod arcade;
+pub mod beam;
 pub mod intcode;
+pub mod springscript;
@@ -0,0 +1,333 @@
+//! Springscript, the language the springdroid of day 21 is programmed in:
+//!
+//! ```text
+//! NOT A J
+//! NOT C T
+//! OR T J
+//! AND D J
+//! WALK
+//! ```
+//!
+//! Instructions read their first operand from a sensor (`A` to `D` when walking, up to `I` when
+//! running) or from the `T` and `J` registers, and write to `T` or `J`. The droid jumps whenever
+//! `J` ends up true. A script holds at most 15 instructions and ends with `WALK` or `RUN`.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::intcode::{Ascii, AsciiOutput, IntcodeError};
+
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// The sensors available in this mode, nearest first.
+    pub fn sensors(&self) -> &'static [char] {
+        match *self {
+            Mode::Walk => &['A', 'B', 'C', 'D'],
+            Mode::Run => &['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I'],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Instruction {
+    pub op: Op,
+    pub x: char,
+    pub y: char,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Script {
+    pub instructions: Vec<Instruction>,
+    pub mode: Mode,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScriptError {
+    UnknownInstruction { line: usize, instruction: String },
+    OperandCount { line: usize, found: usize },
+    InvalidRegister { line: usize, register: String },
+    ReadOnlyRegister { line: usize, register: String },
+    TooManyInstructions { line: usize },
+    MissingMode { line: usize },
+}
+
+impl ScriptError {
+    /// The 1-based line of the script the error was found on.
+    pub fn line(&self) -> usize {
+        match *self {
+            ScriptError::UnknownInstruction { line, .. } |
+            ScriptError::OperandCount { line, .. } |
+            ScriptError::InvalidRegister { line, .. } |
+            ScriptError::ReadOnlyRegister { line, .. } |
+            ScriptError::TooManyInstructions { line } |
+            ScriptError::MissingMode { line } => line,
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScriptError::UnknownInstruction { line, ref instruction } =>
+                write!(f, "line {}: unknown instruction {}", line, instruction),
+            ScriptError::OperandCount { line, found } =>
+                write!(f, "line {}: expected 2 operands, found {}", line, found),
+            ScriptError::InvalidRegister { line, ref register } =>
+                write!(f, "line {}: {} can't be read", line, register),
+            ScriptError::ReadOnlyRegister { line, ref register } =>
+                write!(f, "line {}: {} can't be written, only T and J can", line, register),
+            ScriptError::TooManyInstructions { line } =>
+                write!(f, "line {}: a script holds at most {} instructions", line, MAX_INSTRUCTIONS),
+            ScriptError::MissingMode { line } =>
+                write!(f, "line {}: expected WALK or RUN", line),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl Script {
+    /// Builds a script, checking it the same way the droid would.
+    pub fn new(instructions: Vec<Instruction>, mode: Mode) -> Result<Self, ScriptError> {
+        let script = Script{instructions, mode};
+        script.validate()?;
+        Ok(script)
+    }
+
+    /// Errors report the line the instruction would be on in the script's text.
+    pub fn validate(&self) -> Result<(), ScriptError> {
+        self.instructions.iter().enumerate().try_for_each(|(i, instruction)| check(instruction, i + 1, self.mode, i + 1))
+    }
+}
+
+/// Checks the `count`th instruction of a script, found on `line`.
+fn check(instruction: &Instruction, count: usize, mode: Mode, line: usize) -> Result<(), ScriptError> {
+    if count > MAX_INSTRUCTIONS {
+        return Err(ScriptError::TooManyInstructions { line })
+    }
+    if !mode.sensors().contains(&instruction.x) && instruction.x != 'T' && instruction.x != 'J' {
+        return Err(ScriptError::InvalidRegister { line, register: instruction.x.to_string() })
+    }
+    if instruction.y != 'T' && instruction.y != 'J' {
+        return Err(ScriptError::ReadOnlyRegister { line, register: instruction.y.to_string() })
+    }
+    Ok(())
+}
+
+impl FromStr for Script {
+    type Err = ScriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<_>>();
+        let last = lines.iter().rposition(|line| !line.trim().is_empty()).map(|i| i + 1).unwrap_or(1);
+        let mode = match lines.get(last - 1).map(|line| line.trim()) {
+            Some("WALK") => Mode::Walk,
+            Some("RUN") => Mode::Run,
+            _ => return Err(ScriptError::MissingMode { line: last }),
+        };
+
+        let register = |line: usize, operand: &str| -> Result<char, ScriptError> {
+            let mut chars = operand.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_uppercase() => Ok(c),
+                _ => Err(ScriptError::InvalidRegister { line, register: operand.to_string() }),
+            }
+        };
+
+        let mut instructions = Vec::new();
+        for (i, text) in lines[..last - 1].iter().enumerate() {
+            let line = i + 1;
+            let words = text.split_whitespace().collect::<Vec<_>>();
+            let op = match words.first() {
+                None => continue,
+                Some(&"AND") => Op::And,
+                Some(&"OR") => Op::Or,
+                Some(&"NOT") => Op::Not,
+                Some(word) => return Err(ScriptError::UnknownInstruction { line, instruction: word.to_string() }),
+            };
+            if words.len() != 3 {
+                return Err(ScriptError::OperandCount { line, found: words.len() - 1 })
+            }
+            let instruction = Instruction{op, x: register(line, words[1])?, y: register(line, words[2])?};
+            check(&instruction, instructions.len() + 1, mode, line)?;
+            instructions.push(instruction);
+        }
+
+        Ok(Script{instructions, mode})
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.op, self.x, self.y)
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instruction in &self.instructions {
+            writeln!(f, "{}", instruction)?;
+        }
+        write!(f, "{}", match self.mode {
+            Mode::Walk => "WALK",
+            Mode::Run => "RUN",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Report {
+    /// The droid made it across and reported this much hull damage.
+    Damage(i64),
+    /// The droid fell into space; what the program rendered after receiving the script.
+    Fell(String),
+}
+
+/// Sends `script` to the springdroid `program` and reports how the droid fared.
+pub fn run(program: &[i64], script: &Script) -> Result<Report, IntcodeError> {
+    let mut ascii = Ascii::new(program);
+    // the prompt
+    ascii.run()?;
+    script.to_string().lines().for_each(|line| ascii.send_line(line));
+
+    let mut frame = String::new();
+    for output in ascii.run()? {
+        match output {
+            AsciiOutput::Value(damage) => return Ok(Report::Damage(damage)),
+            AsciiOutput::Line(line) => {
+                frame.push_str(&line);
+                frame.push('\n');
+            },
+        }
+    }
+    Ok(Report::Fell(frame))
+}
+
+/// Tries every script of up to `max_length` instructions, shortest first, and returns the first
+/// one that gets the droid across along with the damage it reported.
+pub fn search(program: &[i64], mode: Mode, max_length: usize) -> Result<Option<(Script, i64)>, IntcodeError> {
+    let ops = [Op::And, Op::Or, Op::Not];
+    let readable = mode.sensors().iter().cloned().chain(vec!['T', 'J']).collect::<Vec<_>>();
+    let candidates = ops.iter().flat_map(|op| readable.iter().flat_map(move |x| ['T', 'J'].iter().map(move |y| Instruction{op: *op, x: *x, y: *y}))).collect::<Vec<_>>();
+
+    for length in 1..=max_length.min(MAX_INSTRUCTIONS) {
+        // counts in base `candidates.len()`, one digit per instruction
+        let mut digits = vec![0; length];
+        loop {
+            let script = Script{instructions: digits.iter().map(|d| candidates[*d]).collect(), mode};
+            if let Report::Damage(damage) = run(program, &script)? {
+                return Ok(Some((script, damage)))
+            }
+
+            match digits.iter().rposition(|d| *d + 1 < candidates.len()) {
+                Some(i) => {
+                    digits[i] += 1;
+                    digits[i + 1..].iter_mut().for_each(|d| *d = 0);
+                },
+                None => break,
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assemble;
+
+    // stands in for the springdroid: reads a walking script and reports a damage of 1000 plus
+    // the number of instructions if there are at least two, or renders a fall otherwise
+    const DROID: &str = r#"
+                out #62
+                out #10
+        read:   in char
+                eq char, #75, done      ; the K of WALK
+                jnz done, #walked
+                eq char, #10, newline
+                add lines, newline, lines
+                jz #0, #read
+        walked: in char
+                lt lines, #2, done
+                jnz done, #fell
+                add lines, #1000, lines
+                out lines
+                halt
+        fell:   out #35
+                out #64
+                out #10
+                halt
+        char:    db 0
+        done:    db 0
+        newline: db 0
+        lines:   db 0
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let script = "NOT A J\nNOT C T\n\nOR T J\nAND D J\nWALK\n".parse::<Script>().unwrap();
+        assert_eq!(script.mode, Mode::Walk);
+        assert_eq!(script.instructions[1], Instruction{op: Op::Not, x: 'C', y: 'T'});
+        assert_eq!(script.to_string(), "NOT A J\nNOT C T\nOR T J\nAND D J\nWALK");
+        assert_eq!("AND H J\nRUN".parse::<Script>().map(|s| s.mode), Ok(Mode::Run));
+    }
+
+    #[test]
+    fn test_errors() {
+        let parse = |s: &str| s.parse::<Script>().map_err(|e| e.to_string());
+        assert_eq!(parse("NOT A J"), Err("line 1: expected WALK or RUN".to_string()));
+        assert_eq!(parse(""), Err("line 1: expected WALK or RUN".to_string()));
+        assert_eq!(parse("NOT A J\nXOR A J\nWALK"), Err("line 2: unknown instruction XOR".to_string()));
+        assert_eq!(parse("NOT A\nWALK"), Err("line 1: expected 2 operands, found 1".to_string()));
+        assert_eq!(parse("NOT A J\n\nAND E J\nWALK"), Err("line 3: E can't be read".to_string()));
+        assert_eq!(parse("NOT AB J\nRUN"), Err("line 1: AB can't be read".to_string()));
+        assert_eq!(parse("NOT A B\nWALK"), Err("line 1: B can't be written, only T and J can".to_string()));
+        assert_eq!(parse(&format!("{}RUN", "NOT A J\n".repeat(16))), Err("line 16: a script holds at most 15 instructions".to_string()));
+
+        let not = Instruction{op: Op::Not, x: 'A', y: 'J'};
+        assert_eq!(Script::new(vec![not; 15], Mode::Walk).map(|s| s.instructions.len()), Ok(15));
+        assert_eq!(Script::new(vec![not; 16], Mode::Walk), Err(ScriptError::TooManyInstructions { line: 16 }));
+    }
+
+    #[test]
+    fn test_run() {
+        let droid = assemble(DROID).unwrap();
+        assert_eq!(run(&droid, &"NOT A J\nWALK".parse().unwrap()), Ok(Report::Fell("#@\n".to_string())));
+        assert_eq!(run(&droid, &"NOT A J\nAND D J\nWALK".parse().unwrap()), Ok(Report::Damage(1002)));
+    }
+
+    #[test]
+    fn test_search() {
+        let droid = assemble(DROID).unwrap();
+        let (script, damage) = search(&droid, Mode::Walk, 3).unwrap().unwrap();
+        assert_eq!(script.to_string(), "AND A T\nAND A T\nWALK");
+        assert_eq!(damage, 1002);
+        assert_eq!(search(&droid, Mode::Walk, 1), Ok(None));
+    }
+}